@@ -0,0 +1,367 @@
+//! SAT-solveurs interchangeables.
+//!
+//! Tous les modes qui résolvent une grille passent par le trait [`SatBackend`], dont les
+//! littéraux sont ceux de `varisat` (`Lit::from_index(i, true)` pour la cellule numéro `i`). En
+//! plus de `varisat`, toujours disponible, et des solveurs externes ([`crate::external`]), les
+//! solveurs `splr` et `batsat` peuvent être compilés avec les features cargo du même nom.
+
+use crate::external::{self, ExternalSolver};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+use varisat::solver::SolverError;
+use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
+
+#[derive(Debug)]
+pub enum Error {
+    Varisat(SolverError),
+    External(external::Error),
+    #[cfg(feature = "splr")]
+    Splr(splr::SolverError),
+
+    /// `batsat` s'est arrêté sans conclure (résultat indéfini)
+    #[cfg(feature = "batsat")]
+    BatsatUndefined,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Varisat(err) => write!(f, "{err}"),
+            Error::External(err) => write!(f, "{err}"),
+            #[cfg(feature = "splr")]
+            Error::Splr(err) => write!(f, "splr: {err}"),
+            #[cfg(feature = "batsat")]
+            Error::BatsatUndefined => write!(f, "batsat: résolution interrompue sans résultat"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Interface commune des SAT-solveurs
+pub trait SatBackend {
+    fn add_clause(&mut self, clause: &[Lit]);
+
+    fn add_formula(&mut self, formula: &CnfFormula) {
+        for clause in formula.iter() {
+            self.add_clause(clause);
+        }
+    }
+
+    /// Hypothèses pour les prochains appels à [`SatBackend::solve`], qui remplacent les
+    /// précédentes
+    fn assume(&mut self, assumptions: &[Lit]);
+
+    /// Indique si la formule est satisfiable sous les hypothèses courantes
+    fn solve(&mut self) -> Result<bool, Error>;
+
+    /// Modèle trouvé par le dernier appel à [`SatBackend::solve`], s'il a réussi
+    fn model(&self) -> Option<Vec<Lit>>;
+
+    /// Hypothèses responsables de l'échec du dernier appel à [`SatBackend::solve`]. Comme pour
+    /// `varisat`, cet ensemble n'est pas forcément minimal.
+    fn failed_core(&self) -> Option<Vec<Lit>>;
+}
+
+/// Solveur à utiliser, tel que choisi en ligne de commande
+#[derive(Clone, Debug)]
+pub enum Backend {
+    Varisat,
+    External {
+        command: String,
+        timeout: Option<Duration>,
+    },
+    #[cfg(feature = "splr")]
+    Splr,
+    #[cfg(feature = "batsat")]
+    Batsat,
+}
+
+impl Backend {
+    /// Crée un solveur vide
+    pub fn create(&self) -> Box<dyn SatBackend> {
+        match self {
+            Backend::Varisat => Box::new(VarisatBackend::default()),
+            Backend::External { command, timeout } => {
+                Box::new(ExternalSolver::new(command.clone(), *timeout))
+            }
+            #[cfg(feature = "splr")]
+            Backend::Splr => Box::new(SplrBackend::default()),
+            #[cfg(feature = "batsat")]
+            Backend::Batsat => Box::new(BatsatBackend::default()),
+        }
+    }
+
+    /// Solveurs intégrés à ce programme: `varisat`, et `splr` et `batsat` s'ils ont été compilés
+    pub fn builtin() -> Vec<Backend> {
+        vec![
+            Backend::Varisat,
+            #[cfg(feature = "splr")]
+            Backend::Splr,
+            #[cfg(feature = "batsat")]
+            Backend::Batsat,
+        ]
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Backend::Varisat => "varisat",
+            Backend::External { command, .. } => command,
+            #[cfg(feature = "splr")]
+            Backend::Splr => "splr",
+            #[cfg(feature = "batsat")]
+            Backend::Batsat => "batsat",
+        }
+    }
+}
+
+/// Solveurs intégrés, par leur nom; les solveurs externes sont décrits par leur commande
+impl FromStr for Backend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "varisat" => Ok(Backend::Varisat),
+            #[cfg(feature = "splr")]
+            "splr" => Ok(Backend::Splr),
+            #[cfg(feature = "batsat")]
+            "batsat" => Ok(Backend::Batsat),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Rassemble les hypothèses qui apparaissent dans `core`, quel que soit leur signe: certains
+/// solveurs renvoient la négation des hypothèses en échec
+#[cfg(feature = "batsat")]
+fn core_of(assumptions: &[Lit], core: impl IntoIterator<Item = Lit>) -> Vec<Lit> {
+    core.into_iter()
+        .filter_map(|lit| {
+            if assumptions.contains(&lit) {
+                Some(lit)
+            } else if assumptions.contains(&!lit) {
+                Some(!lit)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct VarisatBackend(Solver<'static>);
+
+impl SatBackend for VarisatBackend {
+    fn add_clause(&mut self, clause: &[Lit]) {
+        self.0.add_clause(clause);
+    }
+
+    fn add_formula(&mut self, formula: &CnfFormula) {
+        self.0.add_formula(formula);
+    }
+
+    fn assume(&mut self, assumptions: &[Lit]) {
+        self.0.assume(assumptions);
+    }
+
+    fn solve(&mut self) -> Result<bool, Error> {
+        self.0.solve().map_err(Error::Varisat)
+    }
+
+    fn model(&self) -> Option<Vec<Lit>> {
+        self.0.model()
+    }
+
+    fn failed_core(&self) -> Option<Vec<Lit>> {
+        self.0.failed_core().map(<[Lit]>::to_vec)
+    }
+}
+
+/// `splr` n'est pas incrémental: la formule est conservée et résolue à nouveau à chaque appel,
+/// les hypothèses étant ajoutées sous forme de clauses unitaires
+#[cfg(feature = "splr")]
+#[derive(Default)]
+pub struct SplrBackend {
+    clauses: Vec<Vec<i32>>,
+    assumptions: Vec<Lit>,
+    model: Option<Vec<Lit>>,
+    core: Option<Vec<Lit>>,
+}
+
+#[cfg(feature = "splr")]
+impl SatBackend for SplrBackend {
+    fn add_clause(&mut self, clause: &[Lit]) {
+        self.clauses
+            .push(clause.iter().map(|lit| lit.to_dimacs() as i32).collect());
+    }
+
+    fn assume(&mut self, assumptions: &[Lit]) {
+        self.assumptions = assumptions.to_vec();
+    }
+
+    fn solve(&mut self) -> Result<bool, Error> {
+        use splr::{Certificate, SolverError};
+        use std::convert::TryFrom;
+
+        let mut clauses = self.clauses.clone();
+        clauses.extend(
+            self.assumptions
+                .iter()
+                .map(|lit| vec![lit.to_dimacs() as i32]),
+        );
+
+        self.model = None;
+        self.core = None;
+        match Certificate::try_from(clauses) {
+            Ok(Certificate::SAT(model)) => {
+                self.model = Some(
+                    model
+                        .into_iter()
+                        .map(|lit| Lit::from_dimacs(lit as isize))
+                        .collect(),
+                );
+                Ok(true)
+            }
+            Ok(Certificate::UNSAT)
+            | Err(SolverError::EmptyClause)
+            | Err(SolverError::Inconsistent)
+            | Err(SolverError::RootLevelConflict(_)) => {
+                self.core = Some(self.assumptions.clone());
+                Ok(false)
+            }
+            Err(err) => Err(Error::Splr(err)),
+        }
+    }
+
+    fn model(&self) -> Option<Vec<Lit>> {
+        self.model.clone()
+    }
+
+    fn failed_core(&self) -> Option<Vec<Lit>> {
+        self.core.clone()
+    }
+}
+
+#[cfg(feature = "batsat")]
+#[derive(Default)]
+pub struct BatsatBackend {
+    solver: batsat::BasicSolver,
+    assumptions: Vec<Lit>,
+}
+
+#[cfg(feature = "batsat")]
+impl BatsatBackend {
+    fn lit(&mut self, lit: Lit) -> batsat::Lit {
+        use batsat::SolverInterface;
+
+        while self.solver.num_vars() as usize <= lit.index() {
+            self.solver.new_var_default();
+        }
+        batsat::Lit::new(
+            batsat::Var::unsafe_from_idx(lit.index() as u32),
+            lit.is_positive(),
+        )
+    }
+}
+
+#[cfg(feature = "batsat")]
+impl SatBackend for BatsatBackend {
+    fn add_clause(&mut self, clause: &[Lit]) {
+        use batsat::SolverInterface;
+
+        let mut clause = clause.iter().map(|&lit| self.lit(lit)).collect();
+        self.solver.add_clause_reuse(&mut clause);
+    }
+
+    fn assume(&mut self, assumptions: &[Lit]) {
+        self.assumptions = assumptions.to_vec();
+    }
+
+    fn solve(&mut self) -> Result<bool, Error> {
+        use batsat::SolverInterface;
+
+        let assumptions: Vec<_> = self.assumptions.clone();
+        let assumptions: Vec<_> = assumptions.into_iter().map(|lit| self.lit(lit)).collect();
+        let result = self.solver.solve_limited(&assumptions);
+        if result == batsat::lbool::TRUE {
+            Ok(true)
+        } else if result == batsat::lbool::FALSE {
+            Ok(false)
+        } else {
+            Err(Error::BatsatUndefined)
+        }
+    }
+
+    fn model(&self) -> Option<Vec<Lit>> {
+        use batsat::SolverInterface;
+
+        let model = self.solver.get_model();
+        if model.is_empty() {
+            return None;
+        }
+        Some(
+            model
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| **value != batsat::lbool::UNDEF)
+                .map(|(index, value)| Lit::from_index(index, *value == batsat::lbool::TRUE))
+                .collect(),
+        )
+    }
+
+    fn failed_core(&self) -> Option<Vec<Lit>> {
+        use batsat::SolverInterface;
+
+        let core = self
+            .solver
+            .unsat_core()
+            .iter()
+            .map(|lit| Lit::from_index(lit.var().idx() as usize, lit.sign()));
+        Some(core_of(&self.assumptions, core))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Même scénario pour chaque solveur intégré: (x1 ∨ x2) ∧ (¬x1 ∨ x2), puis l'hypothèse ¬x2
+    fn scenario(backend: &Backend) {
+        let mut solver = backend.create();
+        let (x1, x2) = (Lit::from_index(0, true), Lit::from_index(1, true));
+
+        solver.add_clause(&[x1, x2]);
+        solver.add_clause(&[!x1, x2]);
+
+        assert!(solver.solve().unwrap(), "{}", backend.name());
+        assert!(solver.model().unwrap().contains(&x2));
+
+        solver.assume(&[!x2]);
+        assert!(!solver.solve().unwrap(), "{}", backend.name());
+        assert_eq!(solver.failed_core().unwrap(), vec![!x2]);
+
+        solver.assume(&[]);
+        assert!(solver.solve().unwrap(), "{}", backend.name());
+    }
+
+    #[test]
+    fn builtin_backends() {
+        for backend in Backend::builtin() {
+            scenario(&backend);
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert!(matches!("varisat".parse(), Ok(Backend::Varisat)));
+        assert!("minisat".parse::<Backend>().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "batsat")]
+    fn negated_core() {
+        let x = Lit::from_index(3, true);
+        assert_eq!(core_of(&[x], vec![!x]), vec![x]);
+    }
+}
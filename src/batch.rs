@@ -0,0 +1,376 @@
+//! Résolution en lot de toutes les grilles d'un fichier ou d'un dossier de collections
+use inf402_takuzu::backend::Backend;
+use inf402_takuzu::collection::{self, Puzzle};
+use inf402_takuzu::estimate::{self, Limits};
+use inf402_takuzu::json;
+use inf402_takuzu::progress::Silent;
+use inf402_takuzu::rules::EncodingOptions;
+use inf402_takuzu::solve::{self, Status};
+use inf402_takuzu::Grid;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Champ CSV (RFC 4180): entre guillemets, en doublant les guillemets, s'il contient une virgule,
+/// un guillemet ou une fin de ligne
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.into()
+    }
+}
+
+/// Résultat de la résolution d'une grille
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub file: String,
+    pub name: String,
+    pub metadata: Vec<(String, String)>,
+    pub size: Option<usize>,
+    pub status: Status,
+    pub encoding_ms: f64,
+    pub solving_ms: f64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub total: usize,
+    pub solved: usize,
+    pub unsat: usize,
+    pub ambiguous: usize,
+    pub errors: usize,
+    pub encoding_ms: f64,
+    pub solving_ms: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub puzzles: Vec<Entry>,
+    pub summary: Summary,
+}
+
+/// Liste les fichiers à traiter: le fichier lui-même, ou les `.grd` et `.json` d'un dossier
+/// (triés par nom)
+pub fn collect_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_owned()]);
+    }
+
+    let mut files = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .filter(|path| match path {
+            Ok(path) => {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext == "grd" || ext == "json")
+            }
+            Err(_) => true,
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    files.sort();
+
+    Ok(files)
+}
+
+fn error_entry(file: &str, name: &str, error: impl ToString) -> Entry {
+    Entry {
+        file: file.into(),
+        name: name.into(),
+        metadata: Vec::new(),
+        size: None,
+        status: Status::Error,
+        encoding_ms: 0.0,
+        solving_ms: 0.0,
+        error: Some(error.to_string()),
+    }
+}
+
+fn solve_puzzle(
+    file: &str,
+    puzzle: Puzzle,
+    options: &EncodingOptions,
+    limits: &Limits,
+    backend: &Backend,
+) -> Entry {
+    let grid = puzzle.grid.map_err(|err| err.to_string()).and_then(|grid| {
+        estimate::estimate(grid.size(), options)
+            .check(limits, options)
+            .map(|()| grid)
+            .map_err(|err| err.to_string())
+    });
+    let grid = match grid {
+        Ok(grid) => grid,
+        Err(err) => {
+            let mut entry = error_entry(file, &puzzle.name, err);
+            entry.metadata = puzzle.metadata;
+            return entry;
+        }
+    };
+
+    match solve::solve_with_backend(&grid, options, &mut *backend.create(), &mut Silent) {
+        Ok((outcome, timings)) => Entry {
+            file: file.into(),
+            name: puzzle.name,
+            metadata: puzzle.metadata,
+            size: Some(grid.size()),
+            status: Status::from(&outcome),
+            encoding_ms: timings.encoding.as_secs_f64() * 1000.0,
+            solving_ms: timings.solving.as_secs_f64() * 1000.0,
+            error: None,
+        },
+        Err(err) => error_entry(file, &puzzle.name, err),
+    }
+}
+
+/// Nom des grilles sans en-tête d'un fichier: son nom sans extension
+fn default_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Lis les grilles d'un fichier: une collection, ou une grille JSON seule
+fn read_puzzles(path: &Path, default_name: &str) -> Result<Vec<Puzzle>, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+
+    if json::is_json(&content) {
+        // Une grille JSON est traitée comme une collection d'une seule grille
+        let grid = json::parse_grid(&content).map_err(|err| err.to_string())?;
+        return Ok(vec![Puzzle {
+            name: default_name.into(),
+            metadata: Vec::new(),
+            grid: Ok(grid),
+        }]);
+    }
+
+    collection::parse(&content, default_name).map_err(|err| err.to_string())
+}
+
+/// Lis toutes les grilles valides de `files`, nommées `<fichier>:<nom>`
+pub fn load(files: &[PathBuf]) -> Result<Vec<(String, Grid)>, String> {
+    let mut grids = Vec::new();
+    for path in files {
+        let puzzles = read_puzzles(path, &default_name(path))
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        for puzzle in puzzles {
+            let name = format!("{}:{}", path.display(), puzzle.name);
+            let grid = puzzle.grid.map_err(|err| format!("{name}: {err}"))?;
+            grids.push((name, grid));
+        }
+    }
+    Ok(grids)
+}
+
+/// Résoud toutes les grilles de `files` avec `backend`, encodées selon `options`; les grilles
+/// dont la formule dépasserait `limits` sont des erreurs. `on_entry` est appelée après chaque
+/// grille, pour afficher la progression.
+pub fn run(
+    files: &[PathBuf],
+    options: &EncodingOptions,
+    limits: &Limits,
+    backend: &Backend,
+    mut on_entry: impl FnMut(&Entry),
+) -> Report {
+    let mut report = Report::default();
+
+    for path in files {
+        let file = path.display().to_string();
+        let default_name = default_name(path);
+        let puzzles = read_puzzles(path, &default_name);
+
+        let entries = match puzzles {
+            Ok(puzzles) => puzzles
+                .into_iter()
+                .map(|puzzle| solve_puzzle(&file, puzzle, options, limits, backend))
+                .collect(),
+            Err(err) => vec![error_entry(&file, &default_name, err)],
+        };
+
+        for entry in entries {
+            on_entry(&entry);
+            report.push(entry);
+        }
+    }
+
+    report
+}
+
+impl Report {
+    fn push(&mut self, entry: Entry) {
+        let summary = &mut self.summary;
+        summary.total += 1;
+        match entry.status {
+            Status::Solved => summary.solved += 1,
+            Status::Unsat => summary.unsat += 1,
+            Status::Ambiguous => summary.ambiguous += 1,
+            Status::Error => summary.errors += 1,
+        }
+        summary.encoding_ms += entry.encoding_ms;
+        summary.solving_ms += entry.solving_ms;
+
+        self.puzzles.push(entry);
+    }
+
+    /// Tableau lisible, suivi du bilan
+    pub fn write_text(&self, mut w: impl Write) -> io::Result<()> {
+        for entry in &self.puzzles {
+            writeln!(
+                w,
+                "{:<10} {:>10.2} ms {:>10.2} ms  {}:{}{}",
+                entry.status.as_str(),
+                entry.encoding_ms,
+                entry.solving_ms,
+                entry.file,
+                entry.name,
+                entry
+                    .error
+                    .as_ref()
+                    .map(|err| format!(" ({err})"))
+                    .unwrap_or_default(),
+            )?;
+        }
+
+        let Summary {
+            total,
+            solved,
+            unsat,
+            ambiguous,
+            errors,
+            encoding_ms,
+            solving_ms,
+        } = self.summary;
+        writeln!(
+            w,
+            "{total} grilles: {solved} résolues, {unsat} insatisfiables, {ambiguous} ambiguës, \
+            {errors} erreurs (encodage {encoding_ms:.2} ms, résolution {solving_ms:.2} ms)",
+        )
+    }
+
+    /// Une ligne par grille, puis, après une ligne vide, le bilan avec ses propres en-têtes
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "file,name,size,status,encoding_ms,solving_ms,error")?;
+        for entry in &self.puzzles {
+            writeln!(
+                w,
+                "{},{},{},{},{:.3},{:.3},{}",
+                csv_field(&entry.file),
+                csv_field(&entry.name),
+                entry.size.map(|s| s.to_string()).unwrap_or_default(),
+                entry.status.as_str(),
+                entry.encoding_ms,
+                entry.solving_ms,
+                csv_field(entry.error.as_deref().unwrap_or_default()),
+            )?;
+        }
+
+        let Summary {
+            total,
+            solved,
+            unsat,
+            ambiguous,
+            errors,
+            encoding_ms,
+            solving_ms,
+        } = self.summary;
+        writeln!(
+            w,
+            "\ntotal,solved,unsat,ambiguous,errors,encoding_ms,solving_ms\n\
+            {total},{solved},{unsat},{ambiguous},{errors},{encoding_ms:.3},{solving_ms:.3}"
+        )
+    }
+
+    pub fn write_json(&self, mut w: impl Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut w, self)?;
+        writeln!(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields() {
+        assert_eq!(csv_field("simple"), "simple");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("dit \"oui\""), "\"dit \"\"oui\"\"\"");
+        assert_eq!(csv_field("a\r\nb"), "\"a\r\nb\"");
+    }
+
+    #[test]
+    fn report() {
+        let mut report = Report::default();
+        report.push(solve_puzzle(
+            "a.grd",
+            collection::parse(
+                "@ facile\n4\n.1.0\n..0.\n.0..\n11.0\n@ vide\n2\n..\n..\n",
+                "",
+            )
+            .unwrap()
+            .remove(0),
+            &EncodingOptions::default(),
+            &Limits::default(),
+            &Backend::Varisat,
+        ));
+        report.push(error_entry("b.grd", "cassée, vraiment", "oups"));
+
+        assert_eq!(report.summary.total, 2);
+        assert_eq!(report.summary.solved, 1);
+        assert_eq!(report.summary.errors, 1);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[1].starts_with("a.grd,facile,4,solved,"));
+        assert_eq!(
+            lines[2],
+            "b.grd,\"cassée, vraiment\",,error,0.000,0.000,oups"
+        );
+        assert_eq!(lines[3], "");
+        assert_eq!(
+            lines[4],
+            "total,solved,unsat,ambiguous,errors,encoding_ms,solving_ms"
+        );
+        assert!(lines[5].starts_with("2,1,0,0,1,"), "{}", lines[5]);
+        let totals: Vec<f64> = lines[5]
+            .split(',')
+            .skip(5)
+            .map(|ms| ms.parse().unwrap())
+            .collect();
+        assert!((totals[0] - report.summary.encoding_ms).abs() < 0.001);
+        assert!((totals[1] - report.summary.solving_ms).abs() < 0.001);
+
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["puzzles"][0]["status"], "solved");
+        assert_eq!(json["summary"]["errors"], 1);
+    }
+
+    #[test]
+    fn too_large() {
+        let puzzle = collection::parse("@ vide\n4\n....\n....\n....\n....\n", "")
+            .unwrap()
+            .remove(0);
+        let limits = Limits {
+            max_clauses: 100,
+            ..Limits::default()
+        };
+        let entry = solve_puzzle(
+            "a.grd",
+            puzzle,
+            &EncodingOptions::default(),
+            &limits,
+            &Backend::Varisat,
+        );
+
+        assert_eq!(entry.status, Status::Error);
+        assert!(entry.error.unwrap().starts_with("formule trop grande"));
+    }
+}
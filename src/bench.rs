@@ -0,0 +1,360 @@
+//! Banc d'essai: durées, taille de la formule et mémoire pour chaque grille, chaque combinaison
+//! d'encodages et chaque solveur.
+//!
+//! La mémoire n'est mesurée qu'avec la feature cargo `peak-memory`, qui installe [`PeakAlloc`]
+//! comme allocateur du programme: sans elle, les autres modes n'en paient pas le coût à chaque
+//! allocation.
+use crate::batch::csv_field;
+use inf402_takuzu::backend::Backend;
+use inf402_takuzu::estimate::{self, Limits};
+use inf402_takuzu::generate::{self, XorShift};
+use inf402_takuzu::progress::Silent;
+use inf402_takuzu::rules::{self, BalanceEncoding, EncodingOptions, RuleSet, UniquenessEncoding};
+use inf402_takuzu::solve::{self, Status};
+use inf402_takuzu::Grid;
+use serde::Serialize;
+#[cfg(feature = "peak-memory")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{self, Write};
+#[cfg(feature = "peak-memory")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+#[cfg(feature = "peak-memory")]
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "peak-memory")]
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Allocateur du système, qui compte en plus la mémoire allouée et son maximum. C'est
+/// l'allocateur du programme: la mémoire des solveurs externes n'est pas comptée.
+#[cfg(feature = "peak-memory")]
+pub struct PeakAlloc;
+
+#[cfg(feature = "peak-memory")]
+impl PeakAlloc {
+    fn allocated(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "peak-memory")]
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            PeakAlloc::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            PeakAlloc::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            PeakAlloc::allocated(new_size);
+        }
+        new
+    }
+}
+
+/// Ramène le maximum à la mémoire allouée actuellement, et renvoie celle-ci; `None` si la
+/// mémoire n'est pas mesurée
+#[cfg(feature = "peak-memory")]
+fn reset_peak() -> Option<usize> {
+    let allocated = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(allocated, Ordering::Relaxed);
+    Some(allocated)
+}
+
+#[cfg(not(feature = "peak-memory"))]
+fn reset_peak() -> Option<usize> {
+    None
+}
+
+/// Maximum de la mémoire allouée depuis [`reset_peak`], en plus de `baseline`
+#[cfg(feature = "peak-memory")]
+fn peak_since(baseline: Option<usize>) -> Option<usize> {
+    baseline.map(|baseline| PEAK.load(Ordering::Relaxed).saturating_sub(baseline))
+}
+
+#[cfg(not(feature = "peak-memory"))]
+fn peak_since(_: Option<usize>) -> Option<usize> {
+    None
+}
+
+/// Mesure d'une grille, pour une combinaison d'encodages et un solveur
+#[derive(Debug, Serialize)]
+pub struct Run {
+    pub puzzle: String,
+    pub size: usize,
+    pub balance: &'static str,
+    pub uniqueness: &'static str,
+    pub backend: String,
+
+    /// Issue de la résolution, ou `skipped` si la formule dépassait les limites
+    pub status: &'static str,
+    pub variables: usize,
+    pub clauses: usize,
+    pub encoding_ms: f64,
+    pub solving_ms: f64,
+
+    /// Maximum de la mémoire allouée pendant l'encodage et la résolution, en plus de celle qui
+    /// l'était déjà; `None` sans la feature `peak-memory`, ou si la mesure n'a pas eu lieu
+    pub peak_memory_bytes: Option<usize>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub runs: Vec<Run>,
+}
+
+/// Génère `count` grilles aléatoires pour chaque taille de `sizes`, chaque cellule étant donnée
+/// avec la probabilité `density`
+pub fn generate(
+    sizes: &[usize],
+    count: usize,
+    density: f64,
+    seed: u64,
+) -> Result<Vec<(String, Grid)>, generate::Error> {
+    let mut rng = XorShift::new(seed);
+    let mut puzzles = Vec::new();
+
+    for &size in sizes {
+        for index in 0..count {
+            let grid = generate::random_puzzle(size, density, &mut rng)?;
+            puzzles.push((format!("{size}x{size}#{index}"), grid));
+        }
+    }
+
+    Ok(puzzles)
+}
+
+/// Mesure une grille avec des encodages et un solveur
+fn measure(name: &str, grid: &Grid, options: &EncodingOptions, backend: &Backend) -> Run {
+    let mut run = Run {
+        puzzle: name.into(),
+        size: grid.size(),
+        balance: options.balance.name(),
+        uniqueness: options.uniqueness.name(),
+        backend: backend.name().into(),
+        status: Status::Error.as_str(),
+        variables: 0,
+        clauses: 0,
+        encoding_ms: 0.0,
+        solving_ms: 0.0,
+        peak_memory_bytes: None,
+        error: None,
+    };
+
+    let baseline = reset_peak();
+
+    let start = Instant::now();
+    let (formula, _) = rules::encode(grid, options, &mut Silent);
+    run.encoding_ms = start.elapsed().as_secs_f64() * 1000.0;
+    run.variables = formula.var_count();
+    run.clauses = formula.len();
+
+    let mut solver = backend.create();
+    solver.add_formula(&formula);
+    let start = Instant::now();
+    let outcome = solve::find_solutions(grid, options, &mut *solver);
+    run.solving_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    run.peak_memory_bytes = peak_since(baseline);
+    match outcome {
+        Ok(outcome) => run.status = Status::from(&outcome).as_str(),
+        Err(err) => run.error = Some(err.to_string()),
+    }
+    run
+}
+
+/// Mesure chaque grille de `puzzles` avec chaque combinaison d'encodages des règles `rules` et
+/// chaque solveur de `backends`. Les combinaisons dont la formule dépasserait `limits` sont
+/// notées `skipped` sans être générées. `on_run` est appelée après chaque mesure.
+pub fn run(
+    puzzles: &[(String, Grid)],
+    rules: RuleSet,
+    backends: &[Backend],
+    limits: &Limits,
+    mut on_run: impl FnMut(&Run),
+) -> Report {
+    let mut report = Report::default();
+
+    for (name, grid) in puzzles {
+        for balance in BalanceEncoding::ALL {
+            for uniqueness in UniquenessEncoding::ALL {
+                let options = EncodingOptions {
+                    rules,
+                    balance,
+                    uniqueness,
+                };
+                let check = estimate::estimate(grid.size(), &options).check(limits, &options);
+
+                for backend in backends {
+                    let run = match &check {
+                        Ok(()) => measure(name, grid, &options, backend),
+                        Err(err) => Run {
+                            puzzle: name.clone(),
+                            size: grid.size(),
+                            balance: balance.name(),
+                            uniqueness: uniqueness.name(),
+                            backend: backend.name().into(),
+                            status: "skipped",
+                            variables: 0,
+                            clauses: 0,
+                            encoding_ms: 0.0,
+                            solving_ms: 0.0,
+                            peak_memory_bytes: None,
+                            error: Some(err.to_string()),
+                        },
+                    };
+                    on_run(&run);
+                    report.runs.push(run);
+                }
+            }
+        }
+    }
+
+    report
+}
+
+impl Report {
+    /// Tableau lisible, une ligne par mesure
+    pub fn write_text(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(
+            w,
+            "{:<14} {:<12} {:<9} {:<10} {:<9} {:>9} {:>10} {:>12} {:>12} {:>10}",
+            "grille",
+            "règle 1",
+            "règle 3",
+            "solveur",
+            "issue",
+            "variables",
+            "clauses",
+            "encodage",
+            "résolution",
+            "mémoire"
+        )?;
+        for run in &self.runs {
+            writeln!(
+                w,
+                "{:<14} {:<12} {:<9} {:<10} {:<9} {:>9} {:>10} {:>9.2} ms {:>9.2} ms {:>10}",
+                run.puzzle,
+                run.balance,
+                run.uniqueness,
+                run.backend,
+                run.status,
+                run.variables,
+                run.clauses,
+                run.encoding_ms,
+                run.solving_ms,
+                match run.peak_memory_bytes {
+                    Some(bytes) => format!("{} Kio", bytes >> 10),
+                    None => "-".into(),
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Une ligne par mesure
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(
+            w,
+            "puzzle,size,balance,uniqueness,backend,status,variables,clauses,encoding_ms,\
+            solving_ms,peak_memory_bytes,error"
+        )?;
+        for run in &self.runs {
+            writeln!(
+                w,
+                "{},{},{},{},{},{},{},{},{:.3},{:.3},{},{}",
+                csv_field(&run.puzzle),
+                run.size,
+                run.balance,
+                run.uniqueness,
+                csv_field(&run.backend),
+                run.status,
+                run.variables,
+                run.clauses,
+                run.encoding_ms,
+                run.solving_ms,
+                run.peak_memory_bytes
+                    .map_or_else(String::new, |bytes| bytes.to_string()),
+                csv_field(run.error.as_deref().unwrap_or_default()),
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json(&self, mut w: impl Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut w, self)?;
+        writeln!(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs() {
+        let puzzles = generate(&[4, 6], 1, 0.4, 3).unwrap();
+        assert_eq!(puzzles.len(), 2);
+        assert_eq!(puzzles[1].0, "6x6#0");
+
+        let limits = Limits {
+            max_clauses: 10_000,
+            ..Limits::default()
+        };
+        let mut seen = 0;
+        let report = run(
+            &puzzles,
+            RuleSet::TAKUZU,
+            &[Backend::Varisat],
+            &limits,
+            |_| seen += 1,
+        );
+
+        // Deux grilles, quatre combinaisons d'encodages
+        assert_eq!((report.runs.len(), seen), (8, 8));
+        for run in &report.runs {
+            let skipped = run.size == 6 && run.uniqueness == "expansion";
+            assert_eq!(run.status == "skipped", skipped, "{:?}", run);
+            assert!(skipped || ["solved", "ambiguous"].contains(&run.status));
+            assert_eq!(
+                run.peak_memory_bytes.is_some(),
+                cfg!(feature = "peak-memory") && !skipped
+            );
+        }
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 9);
+        assert!(csv
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("4x4#0,4,combinations,expansion,varisat,"));
+
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["runs"][0]["clauses"], report.runs[0].clauses);
+    }
+}
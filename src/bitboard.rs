@@ -0,0 +1,211 @@
+//! Représentation d'une grille en masques de bits, une paire de masques par rangée et par
+//! colonne: les vérifications des règles se font par ligne entière, en quelques opérations.
+//!
+//! Limitée aux grilles de [`MAX_SIZE`] cellules de côté; au-delà, [`Bitboard::new`] renvoie `None`
+//! et les modules qui l'utilisent parcourent la [`Grid`] cellule par cellule.
+use crate::{Cell, Grid};
+
+/// Taille maximale d'une grille représentable: une ligne tient dans un `u64`
+pub const MAX_SIZE: usize = 64;
+
+/// Une rangée ou une colonne: le bit `i` correspond à sa `i`-ième cellule
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Line {
+    /// Cellules remplies
+    pub known: u64,
+
+    /// Cellules qui valent 1, parmi les cellules remplies
+    pub ones: u64,
+}
+
+impl Line {
+    /// Cellules qui valent `value`
+    pub fn cells(self, value: bool) -> u64 {
+        if value {
+            self.ones
+        } else {
+            self.known & !self.ones
+        }
+    }
+
+    /// Nombre de cellules qui valent `value`
+    pub fn count(self, value: bool) -> usize {
+        self.cells(value).count_ones() as usize
+    }
+
+    /// Cellules vides d'une ligne de `size` cellules
+    pub fn empty(self, size: usize) -> u64 {
+        full(size) & !self.known
+    }
+
+    pub fn is_complete(self, size: usize) -> bool {
+        self.known == full(size)
+    }
+
+    /// Cellules qui font partie de trois cellules consécutives valant `value`
+    pub fn triples(self, value: bool) -> u64 {
+        let cells = self.cells(value);
+        let starts = cells & (cells >> 1) & (cells >> 2);
+        starts | (starts << 1) | (starts << 2)
+    }
+}
+
+/// Masque des `size` premières cellules d'une ligne
+fn full(size: usize) -> u64 {
+    u64::MAX.checked_shr((MAX_SIZE - size) as u32).unwrap_or(0)
+}
+
+/// Positions des bits à 1 de `mask`, dans l'ordre croissant
+pub fn bits(mut mask: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let i = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(i)
+    })
+}
+
+/// Grille en masques de bits, rangées et colonnes tenues à jour ensemble
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Bitboard {
+    size: usize,
+    rows: Vec<Line>,
+    columns: Vec<Line>,
+}
+
+impl Bitboard {
+    /// Grille vide de taille `size`, ou `None` si `size` dépasse [`MAX_SIZE`]
+    pub fn empty(size: usize) -> Option<Self> {
+        if size > MAX_SIZE {
+            return None;
+        }
+        Some(Bitboard {
+            size,
+            rows: vec![Line::default(); size],
+            columns: vec![Line::default(); size],
+        })
+    }
+
+    /// Copie de `grid`, ou `None` si elle est plus grande que [`MAX_SIZE`]
+    pub fn new(grid: &Grid) -> Option<Self> {
+        let mut board = Bitboard::empty(grid.size())?;
+        for (index, &cell) in grid.inner().iter().enumerate() {
+            board.set(index % grid.size(), index / grid.size(), cell);
+        }
+        Some(board)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        let row = self.rows[y];
+        if row.known >> x & 1 == 0 {
+            Cell::Empty
+        } else {
+            Cell::Filled(row.ones >> x & 1 == 1)
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        fn update(line: &mut Line, i: usize, cell: Cell) {
+            let bit = 1 << i;
+            line.known &= !bit;
+            line.ones &= !bit;
+            if let Cell::Filled(value) = cell {
+                line.known |= bit;
+                if value {
+                    line.ones |= bit;
+                }
+            }
+        }
+
+        update(&mut self.rows[y], x, cell);
+        update(&mut self.columns[x], y, cell);
+    }
+
+    /// Rangées (`column == false`) ou colonnes
+    pub fn lines(&self, column: bool) -> &[Line] {
+        if column {
+            &self.columns
+        } else {
+            &self.rows
+        }
+    }
+
+    /// Indique si toutes les cellules sont remplies
+    pub fn is_complete(&self) -> bool {
+        self.rows.iter().all(|row| row.is_complete(self.size))
+    }
+
+    pub fn to_grid(&self) -> Grid {
+        let mut grid = Grid::new(self.size);
+        for y in 0..self.size {
+            for x in 0..self.size {
+                grid.set_cell(x, y, self.get(x, y));
+            }
+        }
+        grid
+    }
+}
+
+/// Coordonnées `(x, y)` de la cellule `i` de la ligne (`column == false`) ou colonne numéro `k`
+pub fn position(k: usize, i: usize, column: bool) -> (usize, usize) {
+    if column {
+        (k, i)
+    } else {
+        (i, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_read;
+
+    #[test]
+    fn lines() {
+        let grid = grid_read::parse("6\n1110.0\n......\n......\n......\n......\n.....1\n").unwrap();
+        let board = Bitboard::new(&grid).unwrap();
+
+        let row = board.lines(false)[0];
+        assert_eq!((row.count(true), row.count(false)), (3, 2));
+        assert_eq!(row.empty(6), 0b010000);
+        assert_eq!(row.triples(true), 0b000111);
+        assert_eq!(row.triples(false), 0);
+        assert!(!row.is_complete(6));
+
+        let column = board.lines(true)[5];
+        assert_eq!((column.known, column.ones), (0b100001, 0b100000));
+        assert_eq!(bits(row.cells(false)).collect::<Vec<_>>(), [3, 5]);
+        assert_eq!(position(5, 0, true), (5, 0));
+
+        assert_eq!(board.get(4, 0), Cell::Empty);
+        assert_eq!(board.get(3, 0), Cell::Filled(false));
+        assert_eq!(board.to_grid().inner(), grid.inner());
+    }
+
+    #[test]
+    fn set_and_limits() {
+        let mut board = Bitboard::empty(MAX_SIZE).unwrap();
+        board.set(63, 2, Cell::Filled(true));
+        assert_eq!(board.lines(true)[63].ones, 0b100);
+        board.set(63, 2, Cell::Filled(false));
+        assert_eq!(
+            board.lines(false)[2],
+            Line {
+                known: 1 << 63,
+                ones: 0
+            }
+        );
+        board.set(63, 2, Cell::Empty);
+        assert_eq!(board.lines(false)[2], Line::default());
+        assert_eq!(board.lines(false)[2].empty(MAX_SIZE), u64::MAX);
+
+        assert!(Bitboard::empty(MAX_SIZE + 2).is_none());
+        assert!(Bitboard::new(&Grid::new(66)).is_none());
+    }
+}
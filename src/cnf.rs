@@ -1,11 +1,14 @@
+//! Représentation des littéraux et des clauses, écriture au format DIMACS ou en formule `varisat`
 use crate::{Cell, Grid};
+use rayon::prelude::*;
 use std::collections::HashSet;
-use std::convert::TryInto;
-use std::fmt::Display;
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{Display, Write as _};
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::num::{NonZeroIsize, NonZeroUsize};
-use varisat::{CnfFormula, Lit};
+use std::ops::Not;
+use varisat::{CnfFormula, ExtendFormula, Lit};
 
 /// Takuzu-focused literal representation, using actual coordinates
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -35,6 +38,17 @@ impl Literal {
     }
 }
 
+impl Not for Literal {
+    type Output = Literal;
+
+    fn not(self) -> Self::Output {
+        Literal {
+            negated: !self.negated,
+            ..self
+        }
+    }
+}
+
 impl Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use std::fmt::Write;
@@ -49,75 +63,219 @@ impl Display for Literal {
 
 impl Grid {
     pub fn to_literals(&self) -> HashSet<Literal> {
-        self.inner
+        self.inner()
             .iter()
             .enumerate()
             .filter_map(|(index, cell)| match *cell {
                 Cell::Empty => None,
-                Cell::Filled(p) => Some(Literal::new(index % self.size, index / self.size, p)),
+                Cell::Filled(p) => Some(Literal::new(index % self.size(), index / self.size(), p)),
             })
             .collect()
     }
 }
 
-/// Un fichier CNF pouvant être produit par ce logiciel
+/// Un fichier CNF pouvant être produit par ce logiciel.
+///
+/// Les clauses sont rangées à plat: les littéraux de toutes les clauses se suivent dans
+/// `literals`, numérotés comme dans le fichier DIMACS, et la clause `i` occupe
+/// `literals[offsets[i]..offsets[i + 1]]`. Un littéral occupe ainsi 4 octets, sans allocation
+/// par clause.
 pub struct CNFFile<F = BufWriter<File>> {
     initial: HashSet<Literal>,
     grid_size: NonZeroUsize,
+
+    /// Nombre de variables auxiliaires, numérotées après les cellules de la grille
+    aux_count: usize,
+
+    /// Nombre de clauses écartées car satisfaites par une cellule donnée
+    removed: usize,
     writer: Option<F>,
-    clauses: Vec<Vec<Literal>>,
+    literals: Vec<i32>,
+
+    /// Début de chaque clause dans `literals`, puis la fin de la dernière
+    offsets: Vec<usize>,
+}
+
+/// Littéral numéroté comme dans le fichier DIMACS
+fn pack(literal: Literal, grid_size: usize) -> i32 {
+    // Les limites de taille refusent les formules bien avant 2^31 variables
+    i32::try_from(literal.into_numeric(grid_size).get())
+        .expect("numéro de variable hors des limites du format DIMACS")
 }
 
 impl<F> CNFFile<F> {
+    /// Fichier dont les premières clauses sont les clauses unitaires des cellules données, dans
+    /// l'ordre des cellules (celui d'un `HashSet` change d'une exécution à l'autre)
+    fn with_writer(grid: &Grid, writer: Option<F>) -> Self {
+        let initial = grid.to_literals();
+        let mut givens: Vec<_> = initial.iter().copied().collect();
+        givens.sort_unstable_by_key(|lit| (lit.y, lit.x));
+
+        let mut cnf = Self {
+            grid_size: grid.size().try_into().unwrap(),
+            aux_count: 0,
+            removed: 0,
+            writer,
+            literals: Vec::new(),
+            offsets: vec![0],
+            initial: HashSet::new(),
+        };
+        cnf.push_multiple(givens.into_iter().map(|lit| vec![lit]));
+        cnf.initial = initial;
+        cnf
+    }
+
     pub fn push(&mut self, clause: Vec<Literal>) {
         self.push_multiple(std::iter::once(clause));
     }
 
     pub fn push_multiple(&mut self, new_clauses: impl IntoIterator<Item = Vec<Literal>>) {
-        let new_clauses = new_clauses.into_iter();
+        let grid_size = self.grid_size.get();
 
+        for clause in new_clauses {
+            if clause.iter().any(|l| self.initial.contains(l)) {
+                self.removed += 1;
+                continue;
+            }
+            self.literals
+                .extend(clause.into_iter().map(|lit| pack(lit, grid_size)));
+            self.offsets.push(self.literals.len());
+        }
+    }
+
+    /// Ajoute des lots de clauses générés en parallèle, dans l'ordre des lots. Chaque lot est
+    /// consommé sur un thread du pool `rayon`, qui écarte les clauses satisfaites par une cellule
+    /// donnée et range les autres à plat; le résultat est celui de [`CNFFile::push_multiple`]
+    /// appelée sur chaque lot.
+    pub fn push_batches<B>(&mut self, batches: impl IndexedParallelIterator<Item = B>)
+    where
+        B: IntoIterator<Item = Vec<Literal>>,
+    {
         let initial = &self.initial;
+        let grid_size = self.grid_size.get();
+
+        // Chaque lot est mis à plat à part, avec des positions relatives au lot
+        let batches: Vec<_> = batches
+            .map(|batch| {
+                let (mut literals, mut ends) = (Vec::new(), Vec::new());
+                let mut removed = 0;
+                for clause in batch {
+                    if clause.iter().any(|l| initial.contains(l)) {
+                        removed += 1;
+                        continue;
+                    }
+                    literals.extend(clause.into_iter().map(|lit| pack(lit, grid_size)));
+                    ends.push(literals.len());
+                }
+                (literals, ends, removed)
+            })
+            .collect();
+
+        for (literals, ends, removed) in batches {
+            let base = self.literals.len();
+            self.removed += removed;
+            self.literals.extend(literals);
+            self.offsets.extend(ends.into_iter().map(|end| base + end));
+        }
+    }
+
+    /// Nombre de clauses écartées par [`CNFFile::push`] car déjà satisfaites par une cellule
+    /// donnée
+    pub fn removed_count(&self) -> usize {
+        self.removed
+    }
+
+    /// Clauses conservées, à partir de la `start`-ième (les clauses unitaires des cellules
+    /// données sont les premières), numérotées comme dans le fichier DIMACS
+    pub fn clauses_from(&self, start: usize) -> impl Iterator<Item = &[i32]> {
+        self.offsets[start..]
+            .windows(2)
+            .map(move |bounds| &self.literals[bounds[0]..bounds[1]])
+    }
+
+    /// Taille de la ligne de `clause` (numérotée comme dans le fichier DIMACS) dans le fichier
+    /// DIMACS, en octets
+    pub fn dimacs_size(&self, clause: &[i32]) -> usize {
+        let literals: usize = clause.iter().map(|lit| lit.to_string().len() + 1).sum();
+        literals + "0\n".len()
+    }
+
+    /// En-tête du fichier DIMACS, précédé de la taille de la grille en commentaire: avec des
+    /// variables auxiliaires, elle ne se déduit pas du nombre de variables (voir
+    /// [`crate::sat::formula_size`])
+    fn header(&self) -> String {
+        format!(
+            "c takuzu size {}\np cnf {} {}\n",
+            self.grid_size,
+            self.variable_count(),
+            self.len()
+        )
+    }
+
+    /// Taille du fichier DIMACS qu'écrirait [`CNFFile::save`], en octets
+    pub fn dimacs_len(&self) -> usize {
+        self.header().len()
+            + self
+                .clauses_from(0)
+                .map(|clause| self.dimacs_size(clause))
+                .sum::<usize>()
+    }
+
+    /// Nombre de clauses, y compris les clauses unitaires des cellules données
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
-        self.clauses
-            .extend(new_clauses.filter(|c| !c.iter().any(|l| initial.contains(l))));
+    /// Nombre total de littéraux, toutes clauses confondues
+    pub fn literal_count(&self) -> usize {
+        self.literals.len()
+    }
+
+    /// Crée une variable auxiliaire et renvoie son littéral positif. Elle est représentée comme
+    /// une cellule hors de la grille (sous la dernière rangée), de sorte que sa numérotation
+    /// suive celle des cellules.
+    pub fn new_variable(&mut self) -> Literal {
+        let size = self.grid_size.get();
+        let index = size * size + self.aux_count;
+        self.aux_count += 1;
+        Literal::new(index % size, index / size, true)
+    }
+
+    /// Nombre de variables auxiliaires créées par [`CNFFile::new_variable`]
+    pub fn aux_count(&self) -> usize {
+        self.aux_count
+    }
+
+    /// Nombre total de variables: cellules de la grille et variables auxiliaires
+    pub fn variable_count(&self) -> usize {
+        self.grid_size.get().pow(2) + self.aux_count
     }
 }
 
 impl<F: Write> CNFFile<F> {
     pub fn new(grid: &Grid, writer: F) -> Self {
-        let initial = grid.to_literals();
-
-        Self {
-            grid_size: grid.size.try_into().unwrap(),
-            writer: Some(writer),
-            clauses: initial.iter().copied().map(|l| vec![l]).collect(),
-            initial,
-        }
+        Self::with_writer(grid, Some(writer))
     }
 
     /// Enregistre le fichier CNF, détruit le `CNFFile` et renvoie le `Write` interieur
-    pub fn save(self) -> std::io::Result<F> {
-        let Self {
-            grid_size,
-            clauses,
-            mut writer,
-            ..
-        } = self;
-
-        let mut writer = writer.take().unwrap();
-
-        writeln!(
-            &mut writer,
-            "p cnf {} {}",
-            grid_size.get().pow(2),
-            clauses.len()
-        )?;
-
-        for clause in clauses {
+    pub fn save(mut self) -> std::io::Result<F> {
+        let mut writer = self.writer.take().unwrap();
+
+        writer.write_all(self.header().as_bytes())?;
+
+        // Chaque ligne est formée en mémoire, puis écrite d'un coup
+        let mut line = String::new();
+        for clause in self.clauses_from(0) {
+            line.clear();
             for literal in clause {
-                write!(&mut writer, "{} ", literal.into_numeric(grid_size.get()))?;
+                write!(&mut line, "{literal} ").unwrap();
             }
-            writeln!(&mut writer, "0")?;
+            line.push_str("0\n");
+            writer.write_all(line.as_bytes())?;
         }
 
         Ok(writer)
@@ -127,27 +285,20 @@ impl<F: Write> CNFFile<F> {
 /// For varisat output
 impl CNFFile<()> {
     pub fn new_varisat(grid: &Grid) -> Self {
-        let initial = grid.to_literals();
-
-        Self {
-            grid_size: grid.size.try_into().unwrap(),
-            writer: None,
-            clauses: initial.iter().copied().map(|l| vec![l]).collect(),
-            initial,
-        }
+        Self::with_writer(grid, None)
     }
 
     pub fn into_varisat(self) -> CnfFormula {
-        let Self {
-            grid_size, clauses, ..
-        } = self;
+        let mut formula = CnfFormula::new();
+        let mut lits = Vec::new();
+
+        for clause in self.clauses_from(0) {
+            lits.clear();
+            lits.extend(clause.iter().map(|&lit| Lit::from_dimacs(lit as isize)));
+            formula.add_clause(&lits);
+        }
 
-        CnfFormula::from(clauses.into_iter().map(|clause| {
-            clause
-                .into_iter()
-                .map(|lit| Lit::from_dimacs(lit.into_numeric(grid_size.get()).get()))
-                .collect::<Vec<_>>()
-        }))
+        formula
     }
 }
 
@@ -171,6 +322,67 @@ mod tests {
 
         let out = String::from_utf8(cnf.save().unwrap()).unwrap();
 
-        assert_eq!(out, "p cnf 4 2\n3 -4 0\n1 4 0\n")
+        assert_eq!(out, "c takuzu size 2\np cnf 4 2\n3 -4 0\n1 4 0\n")
+    }
+
+    #[test]
+    fn aux_variables() {
+        let mut cnf = CNFFile::new(&Grid::new(2), Vec::new());
+
+        let a = cnf.new_variable();
+        let b = cnf.new_variable();
+        cnf.push(vec![!a, Literal::new(1, 0, true)]);
+        cnf.push(vec![a, b]);
+        assert_eq!((cnf.aux_count(), cnf.variable_count()), (2, 6));
+
+        let out = String::from_utf8(cnf.save().unwrap()).unwrap();
+        assert_eq!(out, "c takuzu size 2\np cnf 6 2\n-5 2 0\n5 6 0\n");
+        assert_eq!(crate::sat::formula_size(out.as_bytes()), Some(2));
+    }
+
+    #[test]
+    fn flat_storage() {
+        let mut grid = Grid::new(2);
+        grid.set(1, 0, false);
+        let clauses = vec![
+            vec![Literal::new(0, 0, true), Literal::new(1, 1, false)],
+            vec![Literal::new(1, 0, false), Literal::new(0, 1, true)],
+            vec![Literal::new(0, 1, false)],
+        ];
+
+        let mut cnf = CNFFile::new_varisat(&grid);
+        cnf.push_multiple(clauses.clone());
+        let expected: Vec<_> = cnf.clauses_from(0).map(<[i32]>::to_vec).collect();
+        assert_eq!(expected, [vec![-2], vec![1, -4], vec![-3]]);
+        assert_eq!((cnf.len(), cnf.literal_count()), (3, 4));
+
+        // Les lots donnent la même formule que `push_multiple`
+        let mut batched = CNFFile::new_varisat(&grid);
+        let batches = vec![clauses[..1].to_vec(), Vec::new(), clauses[1..].to_vec()];
+        batched.push_batches(batches.into_par_iter());
+        let actual: Vec<_> = batched.clauses_from(0).map(<[i32]>::to_vec).collect();
+        assert_eq!((actual, batched.removed_count()), (expected, 1));
+
+        let formula = cnf.into_varisat();
+        assert_eq!((formula.len(), formula.var_count()), (3, 4));
+        assert_eq!(
+            formula.iter().nth(1).unwrap(),
+            [Lit::from_dimacs(1), Lit::from_dimacs(-4)]
+        );
+    }
+
+    #[test]
+    fn removed_and_size() {
+        let mut grid = Grid::new(2);
+        grid.set(0, 0, true);
+        let mut cnf = CNFFile::new(&grid, Vec::new());
+
+        cnf.push(vec![Literal::new(0, 0, true), Literal::new(1, 1, true)]);
+        cnf.push(vec![Literal::new(0, 0, false), Literal::new(1, 1, true)]);
+        assert_eq!((cnf.len(), cnf.removed_count()), (2, 1));
+        assert_eq!(cnf.dimacs_size(&[-1]), 5);
+
+        let expected = cnf.dimacs_len();
+        assert_eq!(cnf.save().unwrap().len(), expected);
     }
 }
@@ -0,0 +1,209 @@
+//! Collections: plusieurs grilles dans un même fichier.
+//!
+//! Chaque grille est précédée d'un en-tête `@ <nom> [clé=valeur ...]`, suivi de la grille au
+//! format décrit dans [`grid_read`]. Un fichier sans aucun en-tête est une collection d'une
+//! seule grille, ce qui permet de traiter les `.grd` habituels de la même façon.
+//!
+//! ```text
+//! @ facile-1 difficulte=facile source=journal
+//! 4
+//! .1.0
+//! ..0.
+//! .0..
+//! 11.0
+//!
+//! @ vide
+//! 4
+//! ....
+//! ....
+//! ....
+//! ....
+//! ```
+
+use crate::grid_read;
+use crate::Grid;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Error {
+    /// L'en-tête n'a pas de nom, ou l'une de ses métadonnées n'est pas de la forme `clé=valeur`
+    InvalidHeader { line: usize, header: String },
+
+    /// Une ligne non vide précède le premier en-tête
+    MissingHeader { line: usize },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidHeader { line, header } => {
+                write!(f, "en-tête invalide (ligne {line}): {header:?}")
+            }
+            Error::MissingHeader { line } => {
+                write!(f, "contenu avant le premier en-tête (ligne {line})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Une grille d'une collection. Les erreurs de grille sont conservées individuellement pour qu'une
+/// grille invalide n'empêche pas de traiter les autres.
+#[derive(Debug)]
+pub struct Puzzle {
+    pub name: String,
+    pub metadata: Vec<(String, String)>,
+    pub grid: Result<Grid, grid_read::Error>,
+}
+
+fn parse_header(line_no: usize, header: &str) -> Result<(String, Vec<(String, String)>), Error> {
+    let invalid = || Error::InvalidHeader {
+        line: line_no,
+        header: header.into(),
+    };
+
+    let mut words = header[1..].split_whitespace();
+    let name = words.next().ok_or_else(invalid)?;
+    let metadata = words
+        .map(|word| {
+            word.split_once('=')
+                .filter(|(key, _)| !key.is_empty())
+                .map(|(key, value)| (key.into(), value.into()))
+                .ok_or_else(invalid)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((name.into(), metadata))
+}
+
+/// Découpe une collection en grilles. `default_name` nomme la grille d'un fichier sans en-tête.
+pub fn parse(content: &str, default_name: &str) -> Result<Vec<Puzzle>, Error> {
+    let lines: Vec<_> = content
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line))
+        .collect();
+
+    if !lines.iter().any(|(_, line)| line.starts_with('@')) {
+        return Ok(vec![Puzzle {
+            name: default_name.into(),
+            metadata: Vec::new(),
+            grid: grid_read::parse_lines(lines),
+        }]);
+    }
+
+    // Grille en cours de lecture: nom, métadonnées et lignes numérotées
+    type Current<'a> = (String, Vec<(String, String)>, Vec<(usize, &'a str)>);
+
+    let mut puzzles = Vec::new();
+    let mut current: Option<Current> = None;
+
+    for (line_no, line) in lines {
+        if line.starts_with('@') {
+            if let Some((name, metadata, body)) = current.take() {
+                puzzles.push(Puzzle {
+                    name,
+                    metadata,
+                    grid: grid_read::parse_lines(body),
+                });
+            }
+
+            let (name, metadata) = parse_header(line_no, line)?;
+            current = Some((name, metadata, Vec::new()));
+        } else if let Some((_, _, body)) = &mut current {
+            body.push((line_no, line));
+        } else if !line.trim().is_empty() {
+            return Err(Error::MissingHeader { line: line_no });
+        }
+    }
+
+    if let Some((name, metadata, body)) = current {
+        puzzles.push(Puzzle {
+            name,
+            metadata,
+            grid: grid_read::parse_lines(body),
+        });
+    }
+
+    Ok(puzzles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLECTION: &str = "\n\
+        @ facile-1 difficulte=facile source=journal\n\
+        4\n.1.0\n..0.\n.0..\n11.0\n\
+        \n\
+        @ cassee\n\
+        4\n.1.0\n..0\n\
+        @ vide\n\
+        2\n..\n..\n";
+
+    #[test]
+    fn collection() {
+        let puzzles = parse(COLLECTION, "ignoré").unwrap();
+
+        assert_eq!(puzzles.len(), 3);
+
+        assert_eq!(puzzles[0].name, "facile-1");
+        assert_eq!(
+            puzzles[0].metadata,
+            vec![
+                ("difficulte".into(), "facile".into()),
+                ("source".into(), "journal".into()),
+            ],
+        );
+        assert_eq!(
+            grid_read::format(puzzles[0].grid.as_ref().unwrap()),
+            "4\n.1.0\n..0.\n.0..\n11.0\n",
+        );
+
+        assert_eq!(puzzles[1].name, "cassee");
+        assert_eq!(
+            puzzles[1].grid.as_ref().unwrap_err(),
+            &grid_read::Error::RowLength {
+                line: 12,
+                expected: 4,
+                found: 3,
+            },
+        );
+
+        assert_eq!(puzzles[2].name, "vide");
+        assert!(puzzles[2].grid.is_ok());
+    }
+
+    #[test]
+    fn single_grid() {
+        let puzzles = parse("2\n..\n..\n", "test").unwrap();
+
+        assert_eq!(puzzles.len(), 1);
+        assert_eq!(puzzles[0].name, "test");
+        assert!(puzzles[0].metadata.is_empty());
+        assert!(puzzles[0].grid.is_ok());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("2\n@ a\n", "test").unwrap_err(),
+            Error::MissingHeader { line: 1 },
+        );
+        assert_eq!(
+            parse("@\n", "test").unwrap_err(),
+            Error::InvalidHeader {
+                line: 1,
+                header: "@".into(),
+            },
+        );
+        assert_eq!(
+            parse("@ a b\n", "test").unwrap_err(),
+            Error::InvalidHeader {
+                line: 1,
+                header: "@ a b".into(),
+            },
+        );
+    }
+}
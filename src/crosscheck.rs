@@ -0,0 +1,328 @@
+//! Vérification croisée: une grille est résolue par trois chemins indépendants, qui doivent
+//! s'accorder sur l'existence, l'unicité et la valeur de la solution:
+//!   - la formule de [`rules::encode`], résolue par `varisat` ([`solve`]);
+//!   - le fichier DIMACS écrit par [`CNFFile`], résolu par un solveur externe (ou à défaut par
+//!     `varisat`, qui relit le fichier), dont le résultat est décodé par [`sat::read_sat_file`];
+//!   - le solveur [`native`], sans SAT-solveur.
+//!
+//! Un désaccord révèle une erreur dans l'encodage des règles ([`rules`]), l'écriture du fichier
+//! DIMACS ou le décodage des résultats ([`sat`]).
+use crate::backend::{SatBackend, VarisatBackend};
+use crate::cnf::{CNFFile, Literal};
+use crate::external::{self, Output};
+use crate::progress::Silent;
+use crate::rules::{self, EncodingOptions};
+use crate::solve::{self, Outcome, Status};
+use crate::{native, sat, validate, Cell, Grid};
+use std::io::Write;
+use std::time::Duration;
+use varisat::dimacs::DimacsParser;
+
+/// Chemin de résolution
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Method {
+    Varisat,
+    Dimacs,
+    Native,
+}
+
+impl Method {
+    pub const ALL: [Method; 3] = [Method::Varisat, Method::Dimacs, Method::Native];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Varisat => "varisat",
+            Method::Dimacs => "dimacs",
+            Method::Native => "native",
+        }
+    }
+}
+
+/// Solveur qui lit le fichier DIMACS
+#[derive(Clone, Debug, Default)]
+pub enum DimacsSolver {
+    /// `varisat`, qui relit le fichier et écrit son résultat comme un solveur externe
+    #[default]
+    Varisat,
+
+    /// Solveur externe, voir [`external`]
+    External {
+        command: String,
+        timeout: Option<Duration>,
+    },
+}
+
+impl DimacsSolver {
+    /// Résout la formule DIMACS `formula`
+    fn run(&self, formula: &[u8]) -> Result<Output, String> {
+        match self {
+            DimacsSolver::Varisat => {
+                let formula = DimacsParser::parse(formula).map_err(|err| err.to_string())?;
+                let mut solver = VarisatBackend::default();
+                solver.add_formula(&formula);
+
+                let mut result = Vec::new();
+                let code = match solver.solve().map_err(|err| err.to_string())? {
+                    true => {
+                        write!(result, "s SATISFIABLE\nv").unwrap();
+                        for lit in solver.model().unwrap() {
+                            write!(result, " {}", lit.to_dimacs()).unwrap();
+                        }
+                        writeln!(result, " 0").unwrap();
+                        10
+                    }
+                    false => {
+                        writeln!(result, "s UNSATISFIABLE").unwrap();
+                        20
+                    }
+                };
+                Ok(Output {
+                    code: Some(code),
+                    result,
+                })
+            }
+            DimacsSolver::External { command, timeout } => {
+                external::run(command, *timeout, |writer| writer.write_all(formula))
+                    .map_err(|err| err.to_string())
+            }
+        }
+    }
+}
+
+/// Formule DIMACS de `grid`, avec une clause qui interdit la grille complète `blocked`
+fn write_dimacs(grid: &Grid, options: &EncodingOptions, blocked: Option<&Grid>) -> Vec<u8> {
+    let mut cnf = CNFFile::new(grid, Vec::new());
+    rules::write_rules(&mut cnf, grid, options, &mut Silent);
+
+    if let Some(blocked) = blocked {
+        let size = blocked.size();
+        cnf.push(
+            blocked
+                .inner()
+                .iter()
+                .enumerate()
+                .filter_map(|(index, cell)| match *cell {
+                    Cell::Filled(value) => Some(Literal::new(index % size, index / size, !value)),
+                    Cell::Empty => None,
+                })
+                .collect(),
+        );
+    }
+
+    cnf.save().expect("écriture en mémoire")
+}
+
+/// Résout la grille par le fichier DIMACS: une première solution, puis une seconde en interdisant
+/// la première
+fn solve_dimacs(
+    grid: &Grid,
+    options: &EncodingOptions,
+    solver: &DimacsSolver,
+) -> Result<Outcome, String> {
+    let find = |blocked: Option<&Grid>| {
+        solver
+            .run(&write_dimacs(grid, options, blocked))?
+            .read(|result| sat::read_sat_file(result, Some(grid.size()), &mut Silent))
+            .map_err(|err| err.to_string())
+    };
+
+    Ok(match find(None)? {
+        None => Outcome::Unsat,
+        Some(first) => match find(Some(&first))? {
+            None => Outcome::Solved(first),
+            Some(second) => Outcome::Ambiguous(first, second),
+        },
+    })
+}
+
+/// Issue d'un chemin de résolution
+#[derive(Debug)]
+pub struct Check {
+    pub method: Method,
+    pub outcome: Result<Outcome, String>,
+}
+
+/// Résultat de la vérification d'une grille
+#[derive(Debug)]
+pub struct Report {
+    /// Issue de chaque chemin, dans l'ordre de [`Method::ALL`]
+    pub checks: Vec<Check>,
+
+    /// Erreurs et désaccords; vide si les chemins s'accordent
+    pub problems: Vec<String>,
+}
+
+impl Report {
+    pub fn agrees(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Issue commune aux chemins, s'ils s'accordent
+    pub fn status(&self) -> Option<Status> {
+        match &self.checks[0].outcome {
+            Ok(outcome) if self.agrees() => Some(Status::from(outcome)),
+            _ => None,
+        }
+    }
+}
+
+/// Indique si la solution `solution` respecte les cellules données de `grid`
+fn extends(grid: &Grid, solution: &Grid) -> bool {
+    grid.size() == solution.size()
+        && grid
+            .inner()
+            .iter()
+            .zip(solution.inner())
+            .all(|(given, cell)| *given == Cell::Empty || given == cell)
+}
+
+/// Résout `grid` par les trois chemins, avec les encodages `options`, et compare les issues
+pub fn crosscheck(grid: &Grid, options: &EncodingOptions, dimacs: &DimacsSolver) -> Report {
+    let checks: Vec<_> = Method::ALL
+        .iter()
+        .map(|&method| Check {
+            method,
+            outcome: match method {
+                Method::Varisat => solve::solve_with_backend(
+                    grid,
+                    options,
+                    &mut VarisatBackend::default(),
+                    &mut Silent,
+                )
+                .map(|(outcome, _)| outcome)
+                .map_err(|err| err.to_string()),
+                Method::Dimacs => solve_dimacs(grid, options, dimacs),
+                Method::Native => native::solve(grid, options.rules).map_err(|err| err.to_string()),
+            },
+        })
+        .collect();
+
+    let mut problems = Vec::new();
+    let mut outcomes = Vec::new();
+    for check in &checks {
+        let name = check.method.name();
+        let outcome = match &check.outcome {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                problems.push(format!("{name}: {err}"));
+                continue;
+            }
+        };
+
+        let solutions = match outcome {
+            Outcome::Solved(solution) => vec![solution],
+            Outcome::Ambiguous(first, second) => {
+                if first.inner() == second.inner() {
+                    problems.push(format!("{name}: deux solutions identiques"));
+                }
+                vec![first, second]
+            }
+            Outcome::Unsat => vec![],
+        };
+        for solution in solutions {
+            if !validate::is_solution(solution, options.rules) {
+                problems.push(format!("{name}: solution qui ne respecte pas les règles"));
+            } else if !extends(grid, solution) {
+                problems.push(format!("{name}: solution qui change des cellules données"));
+            }
+        }
+        outcomes.push((name, outcome));
+    }
+
+    if let Some((first, rest)) = outcomes.split_first() {
+        if rest
+            .iter()
+            .any(|(_, outcome)| Status::from(*outcome) != Status::from(first.1))
+        {
+            let statuses: Vec<_> = outcomes
+                .iter()
+                .map(|(name, outcome)| format!("{name} {}", Status::from(*outcome).as_str()))
+                .collect();
+            problems.push(format!("issues différentes: {}", statuses.join(", ")));
+        } else if let Outcome::Solved(solution) = first.1 {
+            for (name, outcome) in rest {
+                match outcome {
+                    Outcome::Solved(other) if other.inner() != solution.inner() => {
+                        problems.push(format!("solutions différentes: {} et {name}", first.0))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Report { checks, problems }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{self, XorShift};
+    use crate::grid_read;
+    use crate::rules::{BalanceEncoding, RuleSet, UniquenessEncoding};
+
+    #[test]
+    fn agree() {
+        let mut rng = XorShift::new(7);
+        for size in [4, 6] {
+            for density in [0.2, 0.5] {
+                let grid = generate::random_puzzle(size, density, &mut rng).unwrap();
+
+                for rules in [RuleSet::TAKUZU, RuleSet::UNRULY] {
+                    for balance in BalanceEncoding::ALL {
+                        for uniqueness in UniquenessEncoding::ALL {
+                            let options = EncodingOptions {
+                                rules,
+                                balance,
+                                uniqueness,
+                            };
+                            let report = crosscheck(&grid, &options, &DimacsSolver::Varisat);
+                            assert!(report.agrees(), "{:?}", report);
+                            assert!(report.status().is_some());
+                        }
+                    }
+                }
+            }
+        }
+
+        let grid = grid_read::parse("4\n000.\n....\n....\n....\n").unwrap();
+        let report = crosscheck(&grid, &EncodingOptions::default(), &DimacsSolver::Varisat);
+        assert_eq!(report.status(), Some(Status::Unsat));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disagreements() {
+        use crate::external;
+
+        // Faux solveurs: toujours insatisfiable, ou un modèle qui ne vaut que des 0
+        let scripts = [
+            ("crosscheck-unsat", "echo 's UNSATISFIABLE'\nexit 20"),
+            (
+                "crosscheck-zeros",
+                "echo 's SATISFIABLE'\necho 'v -1 -2 -3 -4 -5 -6 -7 -8 0'\nexit 10",
+            ),
+        ];
+        let grid = grid_read::parse("4\n.1.0\n..0.\n.0..\n11.0\n").unwrap();
+
+        for (name, script) in scripts {
+            let path = external::fake_solver(name, script);
+            let solver = DimacsSolver::External {
+                command: path.display().to_string(),
+                timeout: Some(Duration::from_secs(10)),
+            };
+            let report = crosscheck(&grid, &EncodingOptions::default(), &solver);
+
+            assert!(!report.agrees());
+            assert_eq!(report.status(), None);
+            assert!(
+                report
+                    .problems
+                    .iter()
+                    .any(|problem| problem.contains("dimacs")),
+                "{:?}",
+                report.problems
+            );
+        }
+    }
+}
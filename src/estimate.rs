@@ -0,0 +1,405 @@
+//! Estimation de la taille de la formule avant sa génération.
+//!
+//! Les nombres de clauses ne dépendent que de la taille de la grille et des encodages choisis:
+//! ils sont calculés sans rien générer, pour refuser les formules qui épuiseraient la mémoire
+//! (la règle 3 développée a 2^2n clauses par paire de lignes, la règle 1 en combinaisons
+//! C(n, n/2 + 1) par ligne). Ce sont des majorants: les clauses satisfaites par les cellules
+//! données sont écartées pendant la génération.
+use crate::cnf::Literal;
+use crate::rules::{BalanceEncoding, EncodingOptions, UniquenessEncoding};
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+use std::io::{self, Write};
+use std::mem::size_of;
+
+/// Taille prévue de la partie de la formule produite par une règle. Les nombres saturent à
+/// `u128::MAX`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub struct RuleEstimate {
+    pub rule: u8,
+    pub clauses: u128,
+    pub literals: u128,
+    pub aux_variables: u128,
+
+    /// Mémoire nécessaire à la génération, structures intermédiaires comprises, en octets
+    pub memory_bytes: u128,
+}
+
+/// Taille prévue de la formule d'une grille, sans les clauses unitaires des cellules données
+#[derive(Clone, Debug, Serialize)]
+pub struct Estimate {
+    pub size: usize,
+    pub clauses: u128,
+    pub literals: u128,
+    pub aux_variables: u128,
+    pub memory_bytes: u128,
+    pub rules: Vec<RuleEstimate>,
+}
+
+/// Limites au-delà desquelles la formule n'est pas générée
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    pub max_clauses: u128,
+    pub max_memory_bytes: u128,
+}
+
+impl Limits {
+    /// Aucune limite
+    pub const NONE: Limits = Limits {
+        max_clauses: u128::MAX,
+        max_memory_bytes: u128::MAX,
+    };
+}
+
+/// 100 millions de clauses et 4 Gio
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_clauses: 100_000_000,
+            max_memory_bytes: 4 << 30,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// La formule dépasse les limites; `rule` est la règle la plus coûteuse, et `suggestion`
+    /// l'option qui la remplacerait par un encodage plus compact
+    TooLarge {
+        clauses: u128,
+        memory_bytes: u128,
+        rule: u8,
+        suggestion: Option<&'static str>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::TooLarge {
+                clauses,
+                memory_bytes,
+                rule,
+                suggestion,
+            } => {
+                write!(
+                    f,
+                    "formule trop grande: environ {} clauses et {} Mio, surtout pour la règle {}",
+                    clauses,
+                    memory_bytes >> 20,
+                    rule
+                )?;
+                match suggestion {
+                    Some(option) => write!(f, " (essayer `{option}`)"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Coefficient binomial C(n, k), saturé
+fn binomial(n: u128, k: u128) -> u128 {
+    if k > n {
+        return 0;
+    }
+    (0..k.min(n - k)).fold(1u128, |acc, i| {
+        // Exact à chaque étape: le produit de i + 1 entiers consécutifs est divisible par (i + 1)!
+        acc.checked_mul(n - i)
+            .map_or(u128::MAX, |acc| acc / (i + 1))
+    })
+}
+
+/// Mémoire occupée par des clauses stockées comme dans [`crate::cnf::CNFFile`]: une position
+/// par clause et un entier par littéral
+fn clause_memory(clauses: u128, literals: u128) -> u128 {
+    clauses
+        .saturating_mul(size_of::<usize>() as u128)
+        .saturating_add(literals.saturating_mul(size_of::<i32>() as u128))
+}
+
+/// Mémoire occupée par des clauses rangées à part dans des `Vec<Vec<Literal>>`, avant d'être
+/// ajoutées au fichier
+fn pending_memory(clauses: u128, literals: u128) -> u128 {
+    clauses
+        .saturating_mul(size_of::<Vec<Literal>>() as u128)
+        .saturating_add(literals.saturating_mul(size_of::<Literal>() as u128))
+}
+
+/// Calcule la taille de la formule d'une grille `size`×`size` encodée avec `options`
+pub fn estimate(size: usize, options: &EncodingOptions) -> Estimate {
+    let n = size as u128;
+    let half = n / 2;
+    let pairs = n * n.saturating_sub(1) / 2;
+    let mut rules = Vec::new();
+
+    if options.rules.balance {
+        let (clauses, literals, aux_variables) = match options.balance {
+            BalanceEncoding::Combinations => {
+                // Par ligne et par colonne, chaque combinaison de n/2 + 1 cellules, pour chaque
+                // valeur
+                let clauses = binomial(n, half + 1).saturating_mul(4 * n);
+                (clauses, clauses.saturating_mul(half + 1), 0)
+            }
+            BalanceEncoding::Sequential => {
+                // Un compteur de n/2 par ligne, par colonne et par valeur (voir `at_most`)
+                let counters = 4 * n;
+                let k = half;
+                let (clauses, literals) = if n < 2 {
+                    (0, 0)
+                } else {
+                    (k + 1 + (n - 2) * (2 * k + 1), k + 3 + (n - 2) * (5 * k + 1))
+                };
+                (
+                    counters * clauses,
+                    counters * literals,
+                    counters * (n.saturating_sub(1)) * k,
+                )
+            }
+        };
+        // Les clauses par combinaisons sont toutes rangées à part, sur leur thread, avant d'être
+        // ajoutées
+        let pending = match options.balance {
+            BalanceEncoding::Combinations => pending_memory(clauses, literals),
+            BalanceEncoding::Sequential => 0,
+        };
+        rules.push(RuleEstimate {
+            rule: 1,
+            clauses,
+            literals,
+            aux_variables,
+            memory_bytes: clause_memory(clauses, literals).saturating_add(pending),
+        });
+    }
+
+    if options.rules.no_triples {
+        let clauses = 4 * n * n.saturating_sub(2);
+        rules.push(RuleEstimate {
+            rule: 2,
+            clauses,
+            literals: 3 * clauses,
+            aux_variables: 0,
+            memory_bytes: clause_memory(clauses, 3 * clauses),
+        });
+    }
+
+    if options.rules.unique_lines {
+        let estimate = match options.uniqueness {
+            UniquenessEncoding::Expansion => {
+                // Forme paramétrique: 2^2n ensembles de 2n littéraux, gardés pendant toute la
+                // substitution, puis les mêmes clauses pour chaque paire de lignes et de colonnes
+                let parametric = 1u128.checked_shl(2 * size as u32).unwrap_or(u128::MAX);
+                let width = 2 * n;
+                let clauses = parametric.saturating_mul(2 * pairs);
+                let literals = clauses.saturating_mul(width);
+
+                // Table de hachage: capacité en puissance de deux, un octet de contrôle par case
+                let buckets = (width * 8 / 7 + 1).next_power_of_two();
+                let set = 48 + buckets * (2 * size_of::<usize>() as u128 + 1);
+
+                // Un lot de paires, une par thread, est en attente à la fois
+                let batch = (rayon::current_num_threads() as u128).min(pairs);
+                let pending = parametric.saturating_mul(2 * batch);
+                RuleEstimate {
+                    rule: 3,
+                    clauses,
+                    literals,
+                    aux_variables: 0,
+                    memory_bytes: clause_memory(clauses, literals)
+                        .saturating_add(parametric.saturating_mul(set))
+                        .saturating_add(clause_memory(pending, pending.saturating_mul(width))),
+                }
+            }
+            UniquenessEncoding::Tseitin => {
+                // Par paire de lignes: n variables de différence, 2 clauses de 3 littéraux chacune,
+                // et une clause de n littéraux
+                let lines = 2 * pairs;
+                let clauses = lines * (2 * n + 1);
+                let literals = lines * 7 * n;
+                RuleEstimate {
+                    rule: 3,
+                    clauses,
+                    literals,
+                    aux_variables: lines * n,
+                    memory_bytes: clause_memory(clauses, literals),
+                }
+            }
+        };
+        rules.push(estimate);
+    }
+
+    let sum = |field: fn(&RuleEstimate) -> u128| {
+        rules
+            .iter()
+            .map(field)
+            .fold(0u128, |acc, value| acc.saturating_add(value))
+    };
+    Estimate {
+        size,
+        clauses: sum(|rule| rule.clauses),
+        literals: sum(|rule| rule.literals),
+        aux_variables: sum(|rule| rule.aux_variables),
+        memory_bytes: sum(|rule| rule.memory_bytes),
+        rules,
+    }
+}
+
+impl Estimate {
+    /// Vérifie que la formule respecte `limits`, avant de la générer
+    pub fn check(&self, limits: &Limits, options: &EncodingOptions) -> Result<(), Error> {
+        if self.clauses <= limits.max_clauses && self.memory_bytes <= limits.max_memory_bytes {
+            return Ok(());
+        }
+
+        let worst = self
+            .rules
+            .iter()
+            .max_by_key(|rule| rule.memory_bytes)
+            .map_or(0, |rule| rule.rule);
+        let suggestion = match worst {
+            1 if options.balance == BalanceEncoding::Combinations => Some("--balance sequential"),
+            3 if options.uniqueness == UniquenessEncoding::Expansion => {
+                Some("--uniqueness tseitin")
+            }
+            _ => None,
+        };
+
+        Err(Error::TooLarge {
+            clauses: self.clauses,
+            memory_bytes: self.memory_bytes,
+            rule: worst,
+            suggestion,
+        })
+    }
+
+    pub fn write_text(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(
+            w,
+            "grille {0}x{0} (estimation, sans cellule donnée)",
+            self.size
+        )?;
+        writeln!(
+            w,
+            "clauses: {}, littéraux: {}, variables auxiliaires: {}, mémoire: {} Mio",
+            self.clauses,
+            self.literals,
+            self.aux_variables,
+            self.memory_bytes >> 20
+        )?;
+        for rule in &self.rules {
+            writeln!(
+                w,
+                "règle {}: {} clauses, {} littéraux, {} variables auxiliaires, {} Mio",
+                rule.rule,
+                rule.clauses,
+                rule.literals,
+                rule.aux_variables,
+                rule.memory_bytes >> 20
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Écrit l'estimation sur une seule ligne
+    pub fn write_json(&self, mut w: impl Write) -> io::Result<()> {
+        serde_json::to_writer(&mut w, self)?;
+        writeln!(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::Silent;
+    use crate::rules::RuleSet;
+    use crate::stats;
+    use crate::Grid;
+
+    #[test]
+    fn binomials() {
+        assert_eq!(binomial(4, 3), 4);
+        assert_eq!(binomial(30, 16), 145_422_675);
+        assert_eq!(binomial(200, 100), u128::MAX);
+    }
+
+    /// Sur une grille vide, rien n'est écarté: l'estimation est exacte
+    #[test]
+    fn exact_on_empty_grids() {
+        for size in [2, 4, 6] {
+            for balance in [BalanceEncoding::Combinations, BalanceEncoding::Sequential] {
+                for uniqueness in [UniquenessEncoding::Expansion, UniquenessEncoding::Tseitin] {
+                    let options = EncodingOptions {
+                        rules: RuleSet::TAKUZU,
+                        balance,
+                        uniqueness,
+                    };
+                    let estimate = estimate(size, &options);
+                    let actual = stats::analyse(&Grid::new(size), &options, &mut Silent);
+
+                    for (estimate, actual) in estimate.rules.iter().zip(&actual.rules) {
+                        let context = (size, balance, uniqueness, estimate.rule);
+                        assert_eq!(estimate.clauses, actual.clauses as u128, "{:?}", context);
+                        assert_eq!(estimate.literals, actual.literals as u128, "{:?}", context);
+                        assert_eq!(
+                            estimate.aux_variables, actual.aux_variables as u128,
+                            "{:?}",
+                            context
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pending_rule_1() {
+        let options = EncodingOptions {
+            uniqueness: UniquenessEncoding::Tseitin,
+            ..EncodingOptions::default()
+        };
+        let rule = estimate(10, &options).rules[0];
+        assert_eq!(
+            rule.memory_bytes,
+            clause_memory(rule.clauses, rule.literals)
+                + rule.clauses * size_of::<Vec<Literal>>() as u128
+                + rule.literals * size_of::<Literal>() as u128
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let options = EncodingOptions::default();
+        assert!(estimate(8, &options)
+            .check(&Limits::default(), &options)
+            .is_ok());
+
+        let err = estimate(16, &options)
+            .check(&Limits::default(), &options)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TooLarge {
+                rule: 3,
+                suggestion: Some("--uniqueness tseitin"),
+                ..
+            }
+        ));
+
+        let options = EncodingOptions {
+            uniqueness: UniquenessEncoding::Tseitin,
+            ..options
+        };
+        let err = estimate(30, &options)
+            .check(&Limits::default(), &options)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("(essayer `--balance sequential`)"));
+
+        assert!(estimate(200, &options)
+            .check(&Limits::NONE, &options)
+            .is_ok());
+    }
+}
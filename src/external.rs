@@ -0,0 +1,545 @@
+//! Résolution par un SAT-solveur externe (minisat, kissat, glucose...), lancé comme sous-processus.
+//!
+//! La formule est écrite au format DIMACS dans un fichier temporaire. La commande est découpée aux
+//! espaces (sans guillemets); `{in}` y est remplacé par le chemin de la formule, et `{out}` par
+//! celui d'un fichier de résultats pour les solveurs qui, comme minisat, n'écrivent pas le modèle
+//! sur la sortie standard. Sans `{in}`, le chemin de la formule est ajouté en dernier argument.
+//!
+//! Par convention, les solveurs terminent avec le code 10 si la formule est satisfiable et 20
+//! sinon; le code 0 est aussi accepté, le résultat étant alors déduit de la sortie.
+//!
+//! Les fichiers temporaires sont créés dans un dossier propre à chaque résolution, au nom
+//! imprévisible et privé, supprimé ensuite.
+
+use crate::backend::{self, SatBackend};
+use crate::progress::Silent;
+use crate::sat;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::{DirBuilder, File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use varisat::Lit;
+
+#[derive(Debug)]
+pub enum Error {
+    /// La commande est vide
+    EmptyCommand,
+
+    /// Erreur d'entrée/sortie sur les fichiers temporaires
+    Io(io::Error),
+
+    /// Le solveur n'a pas pu être lancé
+    Spawn { program: String, error: io::Error },
+
+    /// Le solveur n'a pas terminé dans le temps imparti, il a été arrêté
+    Timeout(Duration),
+
+    /// Le solveur a terminé avec un code inattendu (`None` s'il a été tué par un signal)
+    Failed(Option<i32>),
+
+    /// La sortie du solveur n'a pas pu être lue
+    Output(sat::Error),
+
+    /// Le code de retour contredit la réponse lue (10 sans modèle ou 20 avec un modèle)
+    Inconsistent { code: i32, satisfiable: bool },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::EmptyCommand => write!(f, "commande du solveur vide"),
+            Error::Io(err) => write!(f, "fichier temporaire: {err}"),
+            Error::Spawn { program, error } => {
+                write!(f, "impossible de lancer {program:?}: {error}")
+            }
+            Error::Timeout(timeout) => write!(f, "délai dépassé ({timeout:?})"),
+            Error::Failed(Some(code)) => write!(f, "le solveur a terminé avec le code {code}"),
+            Error::Failed(None) => write!(f, "le solveur a été interrompu par un signal"),
+            Error::Output(err) => write!(f, "sortie du solveur illisible: {err:?}"),
+            Error::Inconsistent { code, satisfiable } => write!(
+                f,
+                "le solveur a terminé avec le code {code} mais a répondu {}",
+                if *satisfiable { "SAT" } else { "UNSAT" }
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Dossier temporaire d'une résolution, supprimé à la fin avec son contenu. Son nom n'est pas
+/// prévisible et il n'est accessible qu'à l'utilisateur (sous Unix): un autre utilisateur ne
+/// peut ni y lire la formule, ni y placer à l'avance un lien symbolique à la place d'un fichier.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        loop {
+            let random = RandomState::new().build_hasher().finish();
+            let path = std::env::temp_dir().join(format!(
+                "takuzu-{}-{}-{random:016x}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed),
+            ));
+
+            let mut builder = DirBuilder::new();
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            match builder.create(&path) {
+                Ok(()) => return Ok(TempDir(path)),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+
+    /// Crée le fichier `name`, qui ne doit pas déjà exister
+    fn create(&self, name: &str) -> io::Result<File> {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.path(name))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Un SAT-solveur externe. Il n'est pas incrémental: la formule est conservée, et écrite à
+/// nouveau à chaque résolution avec les hypothèses sous forme de clauses unitaires.
+#[derive(Clone, Debug)]
+pub struct ExternalSolver {
+    /// Commande à lancer, voir la documentation du module
+    pub command: String,
+
+    /// Durée maximale de chaque résolution
+    pub timeout: Option<Duration>,
+
+    clauses: Vec<Vec<Lit>>,
+    assumptions: Vec<Lit>,
+    model: Option<Vec<Lit>>,
+    core: Option<Vec<Lit>>,
+}
+
+impl ExternalSolver {
+    pub fn new(command: String, timeout: Option<Duration>) -> Self {
+        ExternalSolver {
+            command,
+            timeout,
+            clauses: Vec::new(),
+            assumptions: Vec::new(),
+            model: None,
+            core: None,
+        }
+    }
+
+    /// Écrit la formule et les hypothèses au format DIMACS
+    fn write_dimacs(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let variables = self
+            .clauses
+            .iter()
+            .flatten()
+            .chain(&self.assumptions)
+            .map(|lit| lit.index() + 1)
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            writer,
+            "p cnf {variables} {}",
+            self.clauses.len() + self.assumptions.len()
+        )?;
+
+        let units = self.assumptions.iter().map(std::slice::from_ref);
+        for clause in self.clauses.iter().map(Vec::as_slice).chain(units) {
+            for lit in clause {
+                write!(writer, "{} ", lit.to_dimacs())?;
+            }
+            writeln!(writer, "0")?;
+        }
+
+        Ok(())
+    }
+
+    /// Lance le solveur; renvoie la valeur des variables du modèle, ou `None` si la formule
+    /// n'est pas satisfiable
+    fn run(&self) -> Result<Option<HashMap<usize, bool>>, Error> {
+        let output = run(&self.command, self.timeout, |writer| {
+            self.write_dimacs(writer)
+        })?;
+        output.read(|result| sat::read_model(result, &mut Silent))
+    }
+}
+
+/// Sortie d'un solveur externe
+#[derive(Clone, Debug)]
+pub struct Output {
+    /// Code de retour du solveur
+    pub code: Option<i32>,
+
+    /// Contenu du fichier de résultats `{out}`, ou à défaut de la sortie standard
+    pub result: Vec<u8>,
+}
+
+impl Output {
+    /// Lit le résultat avec `read` ([`sat::read_model`] ou [`sat::read_sat_file`]); renvoie
+    /// `None` si la formule n'est pas satisfiable, et une erreur si le code de retour contredit
+    /// la réponse
+    pub fn read<T>(
+        &self,
+        read: impl FnOnce(&[u8]) -> Result<T, sat::Error>,
+    ) -> Result<Option<T>, Error> {
+        match read(&self.result) {
+            Ok(_) if self.code == Some(20) => Err(Error::Inconsistent {
+                code: 20,
+                satisfiable: true,
+            }),
+            Ok(model) => Ok(Some(model)),
+            Err(sat::Error::Unsatisfiable) if self.code == Some(10) => Err(Error::Inconsistent {
+                code: 10,
+                satisfiable: false,
+            }),
+            Err(sat::Error::Unsatisfiable) => Ok(None),
+            // Certains solveurs se contentent du code de retour
+            Err(sat::Error::UndefinedModel) if self.code == Some(20) => Ok(None),
+            Err(err) => Err(Error::Output(err)),
+        }
+    }
+}
+
+/// Lance la commande `command` (voir la documentation du module) sur la formule DIMACS écrite
+/// par `formula`, au plus pendant `timeout`
+pub fn run(
+    command: &str,
+    timeout: Option<Duration>,
+    formula: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<Output, Error> {
+    let dir = TempDir::new()?;
+    let input = dir.path("formula.cnf");
+    let stdout = dir.path("solver.out");
+
+    let mut writer = BufWriter::new(dir.create("formula.cnf")?);
+    formula(&mut writer)?;
+    writer.flush()?;
+
+    let mut words = command.split_whitespace();
+    let program = words.next().ok_or(Error::EmptyCommand)?;
+    let mut output = None;
+    let mut has_input = false;
+    let mut args: Vec<String> = words
+        .map(|word| {
+            if word.contains("{in}") {
+                has_input = true;
+            }
+            if word.contains("{out}") {
+                output.get_or_insert_with(|| dir.path("result.res"));
+            }
+            let word = word.replace("{in}", &input.to_string_lossy());
+            match &output {
+                Some(output) => word.replace("{out}", &output.to_string_lossy()),
+                None => word,
+            }
+        })
+        .collect();
+    if !has_input {
+        args.push(input.to_string_lossy().into_owned());
+    }
+
+    let instant_solving = Instant::now();
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::null())
+        // Un fichier plutôt qu'un tube: le solveur ne peut pas rester bloqué sur un tube plein
+        .stdout(dir.create("solver.out")?)
+        .spawn()
+        .map_err(|error| Error::Spawn {
+            program: program.into(),
+            error,
+        })?;
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if let Some(timeout) = timeout {
+            if instant_solving.elapsed() > timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Error::Timeout(timeout));
+            }
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    };
+
+    let code = status.code();
+    if !matches!(code, Some(0) | Some(10) | Some(20)) {
+        return Err(Error::Failed(code));
+    }
+
+    let result = std::fs::read(output.as_ref().unwrap_or(&stdout))?;
+    Ok(Output { code, result })
+}
+
+impl SatBackend for ExternalSolver {
+    fn add_clause(&mut self, clause: &[Lit]) {
+        self.clauses.push(clause.to_vec());
+    }
+
+    fn assume(&mut self, assumptions: &[Lit]) {
+        self.assumptions = assumptions.to_vec();
+    }
+
+    fn solve(&mut self) -> Result<bool, backend::Error> {
+        self.model = None;
+        self.core = None;
+
+        match self.run().map_err(backend::Error::External)? {
+            Some(values) => {
+                self.model = Some(
+                    values
+                        .into_iter()
+                        .map(|(var, value)| Lit::from_index(var - 1, value))
+                        .collect(),
+                );
+                Ok(true)
+            }
+            None => {
+                // Le solveur ne dit pas quelles hypothèses sont en cause
+                self.core = Some(self.assumptions.clone());
+                Ok(false)
+            }
+        }
+    }
+
+    fn model(&self) -> Option<Vec<Lit>> {
+        self.model.clone()
+    }
+
+    fn failed_core(&self) -> Option<Vec<Lit>> {
+        self.core.clone()
+    }
+}
+
+/// Faux solveur des tests: un script shell, supprimé quand il est abandonné
+#[cfg(all(test, unix))]
+pub(crate) struct FakeSolver(PathBuf);
+
+/// Écrit un faux solveur qui exécute `script`
+#[cfg(all(test, unix))]
+pub(crate) fn fake_solver(name: &str, script: &str) -> FakeSolver {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join(format!("takuzu-test-{}-{name}", std::process::id()));
+    std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    FakeSolver(path)
+}
+
+#[cfg(all(test, unix))]
+impl std::ops::Deref for FakeSolver {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(all(test, unix))]
+impl Drop for FakeSolver {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::grid_read;
+    use crate::progress::Silent;
+    use crate::rules::EncodingOptions;
+    use crate::solve::{self, Outcome};
+    use crate::Grid;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    fn solver(path: &Path, args: &str) -> ExternalSolver {
+        ExternalSolver::new(
+            format!("{} {args}", path.display()),
+            Some(Duration::from_secs(10)),
+        )
+    }
+
+    #[test]
+    fn sat_on_stdout() {
+        // Un seul modèle: le faux solveur répond UNSAT une fois sa clause bloquante ajoutée
+        let path = fake_solver(
+            "stdout",
+            "test -s \"$1\" || exit 1\n\
+            grep -q '^-1 2 3 -4 0$' \"$1\" && { echo 's UNSATISFIABLE'; exit 20; }\n\
+            printf 's SATISFIABLE\\nv 1 -2\\nv -3 4 0\\n'\nexit 10",
+        );
+
+        let result = solve::solve_with_backend(
+            &Grid::new(2),
+            &EncodingOptions::default(),
+            &mut solver(&path, ""),
+            &mut Silent,
+        );
+
+        match result.unwrap().0 {
+            Outcome::Solved(solution) => assert_eq!(grid_read::format(&solution), "2\n10\n01\n"),
+            outcome => panic!("{:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn minisat_style() {
+        // minisat <entrée> <sortie>
+        let path = fake_solver("minisat", "printf 'UNSAT\\n' > \"$2\"\nexit 20");
+
+        let mut solver = solver(&path, "{in} {out}");
+        let x = Lit::from_index(0, true);
+        solver.add_clause(&[x]);
+        solver.assume(&[!x]);
+        let sat = solver.solve().unwrap();
+
+        assert!(!sat);
+        assert_eq!(solver.failed_core(), Some(vec![!x]));
+    }
+
+    #[test]
+    fn temp_dir() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.0.clone();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        // Un fichier existant, ou un lien symbolique, n'est jamais réutilisé
+        dir.create("formula.cnf").unwrap();
+        std::os::unix::fs::symlink("/dev/null", dir.path("link")).unwrap();
+        assert!(dir.create("formula.cnf").is_err());
+        assert!(dir.create("link").is_err());
+
+        assert_ne!(TempDir::new().unwrap().0, path);
+        drop(dir);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn dimacs() {
+        let mut solver = ExternalSolver::new("true".into(), None);
+        solver.add_clause(&[Lit::from_index(0, true), Lit::from_index(2, false)]);
+        solver.assume(&[Lit::from_index(1, true)]);
+
+        let mut output = Vec::new();
+        solver.write_dimacs(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "p cnf 3 2\n1 -3 0\n2 0\n"
+        );
+    }
+
+    #[test]
+    fn failures() {
+        let path = fake_solver("crash", "exit 3");
+        let err = solver(&path, "").solve().unwrap_err();
+        assert!(
+            matches!(err, backend::Error::External(Error::Failed(Some(3)))),
+            "{:?}",
+            err
+        );
+
+        let path = fake_solver("slow", "sleep 5");
+        let mut slow =
+            ExternalSolver::new(path.display().to_string(), Some(Duration::from_millis(100)));
+        let err = slow.solve().unwrap_err();
+        assert!(
+            matches!(err, backend::Error::External(Error::Timeout(_))),
+            "{:?}",
+            err
+        );
+
+        let mut missing = ExternalSolver::new("/nonexistent/solver".into(), None);
+        assert!(matches!(
+            missing.solve(),
+            Err(backend::Error::External(Error::Spawn { .. }))
+        ));
+    }
+
+    #[test]
+    fn inconsistent_codes() {
+        let read = |code, result: &str| {
+            Output {
+                code: Some(code),
+                result: result.as_bytes().to_vec(),
+            }
+            .read(|result| sat::read_model(result, &mut Silent))
+        };
+
+        assert_eq!(
+            read(10, "s SATISFIABLE\nv 1 -2 0\n")
+                .unwrap()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(read(20, "s UNSATISFIABLE\n").unwrap().is_none());
+
+        let err = read(20, "s SATISFIABLE\nv 1 -2 0\n").unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::Inconsistent {
+                    code: 20,
+                    satisfiable: true
+                }
+            ),
+            "{:?}",
+            err
+        );
+        let err = read(10, "s UNSATISFIABLE\n").unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::Inconsistent {
+                    code: 10,
+                    satisfiable: false
+                }
+            ),
+            "{:?}",
+            err
+        );
+
+        // Le faux solveur répond SAT mais termine avec le code 20
+        let path = fake_solver("inconsistent", "printf 's SATISFIABLE\\nv 1 0\\n'\nexit 20");
+        let mut solver = solver(&path, "");
+        solver.add_clause(&[Lit::from_index(0, true)]);
+        assert!(matches!(
+            solver.solve(),
+            Err(backend::Error::External(Error::Inconsistent {
+                code: 20,
+                ..
+            }))
+        ));
+    }
+}
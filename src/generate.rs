@@ -0,0 +1,225 @@
+//! Génération de grilles aléatoires, reproductibles à partir d'une graine
+use crate::backend::{self, SatBackend, VarisatBackend};
+use crate::bitboard::Bitboard;
+use crate::progress::Silent;
+use crate::rules::{self, BalanceEncoding, EncodingOptions, RuleSet, UniquenessEncoding};
+use crate::solve::model_to_grid;
+use crate::validate;
+use crate::{Cell, Grid};
+use std::fmt::{Display, Formatter};
+use varisat::Lit;
+
+#[derive(Debug)]
+pub enum Error {
+    /// La taille demandée n'est pas un entier pair strictement positif
+    InvalidSize(usize),
+
+    /// La formule n'a aucune solution: aucun tirage ne pourrait aboutir
+    Unsatisfiable,
+
+    Solver(backend::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidSize(size) => {
+                write!(f, "taille invalide (entier pair attendu): {size}")
+            }
+            Error::Unsatisfiable => write!(f, "aucune grille ne respecte les règles"),
+            Error::Solver(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Self {
+        Error::Solver(err)
+    }
+}
+
+/// Générateur pseudo-aléatoire xorshift64 (Marsaglia, 2003): rapide et sans dépendance, mais
+/// inutilisable en cryptographie
+#[derive(Clone, Debug)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    /// La graine 0, point fixe de xorshift, est remplacée par une autre valeur
+    pub fn new(seed: u64) -> Self {
+        XorShift(if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Entier dans `0..n` (le biais, en n / 2^64, est négligeable ici)
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+}
+
+/// Grille complète aléatoire respectant les règles du Takuzu. `size` doit être pair et non nul.
+///
+/// Quelques cellules tirées au hasard sont imposées au SAT-solveur, jusqu'à ce qu'il trouve une
+/// solution compatible; la solution n'est donc pas tirée uniformément. Les tirages qui
+/// enfreignent déjà une règle sont écartés sur un [`Bitboard`], sans appeler le solveur.
+pub fn random_solution(size: usize, rng: &mut XorShift) -> Result<Grid, Error> {
+    if size == 0 || !size.is_multiple_of(2) {
+        return Err(Error::InvalidSize(size));
+    }
+
+    // Encodages compacts: la génération doit rester rapide jusqu'aux grandes tailles
+    let options = EncodingOptions {
+        rules: RuleSet::TAKUZU,
+        balance: BalanceEncoding::Sequential,
+        uniqueness: UniquenessEncoding::Tseitin,
+    };
+    let (formula, _) = rules::encode(&Grid::new(size), &options, &mut Silent);
+
+    let mut solver = VarisatBackend::default();
+    solver.add_formula(&formula);
+
+    // Sans solution, les tirages échoueraient tous
+    if !solver.solve()? {
+        return Err(Error::Unsatisfiable);
+    }
+
+    loop {
+        let assumptions: Vec<_> = (0..size)
+            .map(|_| (rng.below(size * size), rng.bool()))
+            .collect();
+
+        if let Some(mut board) = Bitboard::empty(size) {
+            let mut contradiction = false;
+            for &(index, value) in &assumptions {
+                let (x, y) = (index % size, index / size);
+                contradiction |= board.get(x, y) == Cell::Filled(!value);
+                board.set(x, y, Cell::Filled(value));
+            }
+            if contradiction || !validate::board_conflicts(&board, RuleSet::TAKUZU).is_empty() {
+                continue;
+            }
+        }
+
+        let assumptions: Vec<_> = assumptions
+            .into_iter()
+            .map(|(index, value)| Lit::from_index(index, value))
+            .collect();
+        solver.assume(&assumptions);
+
+        if solver.solve()? {
+            return Ok(model_to_grid(size, &solver.model().unwrap()));
+        }
+    }
+}
+
+/// Grille à résoudre tirée d'une solution aléatoire, chaque cellule étant donnée avec la
+/// probabilité `density`. La solution n'est pas forcément unique.
+pub fn random_puzzle(size: usize, density: f64, rng: &mut XorShift) -> Result<Grid, Error> {
+    let mut grid = random_solution(size, rng)?;
+
+    for y in 0..size {
+        for x in 0..size {
+            if (rng.next_u64() as f64) >= density * u64::MAX as f64 {
+                grid.set_cell(x, y, Cell::Empty);
+            }
+        }
+    }
+
+    Ok(grid)
+}
+
+/// Grille quelconque, qui n'a pas à respecter les règles: chaque cellule est vide une fois sur
+/// deux, sinon 0 ou 1. Sert à comparer deux implémentations d'une même vérification.
+#[cfg(test)]
+pub(crate) fn random_partial_grid(size: usize, rng: &mut XorShift) -> Grid {
+    let mut grid = Grid::new(size);
+    for y in 0..size {
+        for x in 0..size {
+            match rng.below(4) {
+                0 | 1 => grid.set_cell(x, y, Cell::Empty),
+                value => grid.set(x, y, value == 3),
+            }
+        }
+    }
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate;
+
+    #[test]
+    fn xorshift() {
+        let mut a = XorShift::new(42);
+        let mut b = XorShift::new(42);
+        let values: Vec<_> = (0..5).map(|_| a.next_u64()).collect();
+        assert_eq!(values, (0..5).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert!(values.windows(2).all(|w| w[0] != w[1]));
+
+        assert_ne!(XorShift::new(0).next_u64(), 0);
+        assert!((0..100).all(|_| a.below(6) < 6));
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        let mut rng = XorShift::new(1);
+        assert!(matches!(
+            random_solution(0, &mut rng),
+            Err(Error::InvalidSize(0))
+        ));
+        assert!(matches!(
+            random_puzzle(5, 0.5, &mut rng),
+            Err(Error::InvalidSize(5))
+        ));
+    }
+
+    #[test]
+    fn solutions() {
+        let mut rng = XorShift::new(7);
+        for size in [2, 4, 6, 10] {
+            let solution = random_solution(size, &mut rng).unwrap();
+            assert!(
+                validate::is_solution(&solution, RuleSet::TAKUZU),
+                "{}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn puzzles() {
+        let puzzle = random_puzzle(8, 0.5, &mut XorShift::new(1)).unwrap();
+        let givens = puzzle.to_literals().len();
+        assert!(givens > 10 && givens < 54, "{}", givens);
+
+        // Même graine, même grille
+        let again = random_puzzle(8, 0.5, &mut XorShift::new(1)).unwrap();
+        assert_eq!(again.inner(), puzzle.inner());
+
+        assert_eq!(
+            random_puzzle(4, 1.0, &mut XorShift::new(1))
+                .unwrap()
+                .to_literals()
+                .len(),
+            16
+        );
+    }
+}
@@ -1,67 +1,289 @@
-use crate::Grid;
+//! Lecture et écriture des fichiers de grille (`.grd`).
+//!
+//! Grammaire acceptée:
+//!
+//! ```text
+//! fichier    := taille fin-ligne ligne{taille}
+//! taille     := entier pair non nul
+//! ligne      := séparateur* cellule (séparateur* cellule){taille - 1} séparateur* fin-ligne
+//! cellule    := '0' | '1' | vide
+//! vide       := '.' | '#' | '_' | '-'
+//! séparateur := ' ' | '\t' | ',' | ';' | '|'
+//! fin-ligne  := '\n' | "\r\n"
+//! ```
+//!
+//! Les lignes ne contenant que des séparateurs sont ignorées. La forme canonique, produite par
+//! [`format()`], n'utilise que `0`, `1` et `.`, sans séparateurs.
+
+use crate::progress::{Event, Progress, Silent};
+use crate::{Cell, Grid};
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::path::PathBuf;
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// Le fichier ne contient pas de ligne indiquant la taille
+    MissingSize,
+
+    /// La taille n'est pas un entier pair non nul
+    InvalidSize(String),
+
+    /// Un caractère ne correspond ni à une cellule, ni à un séparateur (numéros à partir de 1)
+    InvalidChar {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+
+    /// Une ligne ne contient pas autant de cellules que la taille de la grille
+    RowLength {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+
+    /// Le fichier ne contient pas autant de lignes que la taille de la grille
+    RowCount { expected: usize, found: usize },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::MissingSize => write!(f, "fichier incorrect: taille manquante"),
+            Error::InvalidSize(size) => write!(f, "taille incorrecte: {size:?}"),
+            Error::InvalidChar {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "caractère {found:?} inattendu (ligne {line}, colonne {column})"
+            ),
+            Error::RowLength {
+                line,
+                expected,
+                found,
+            } => write!(f, "ligne {line}: {found} cellules au lieu de {expected}"),
+            Error::RowCount { expected, found } => {
+                write!(f, "{found} lignes au lieu de {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 pub fn file_read(filepath: PathBuf) -> String {
     fs::read_to_string(filepath).expect("Problème lors de la lecture de la grille")
 }
 
-pub fn size(content: &str) -> &str {
-    let bytes = content.as_bytes();
+fn is_separator(c: char) -> bool {
+    matches!(c, ' ' | '\t' | ',' | ';' | '|')
+}
 
-    for (i, &element) in bytes.iter().enumerate() {
-        if element == b'\n' {
-            return &content[0..i];
-        }
+fn parse_cell(c: char) -> Option<Cell> {
+    match c {
+        '0' => Some(Cell::Filled(false)),
+        '1' => Some(Cell::Filled(true)),
+        '.' | '#' | '_' | '-' => Some(Cell::Empty),
+        _ => None,
     }
-    panic!("Fichier incorrect : size");
 }
 
-pub fn fill_grid_from_file(grid: &mut Grid, content: &str) {
-    let bytes = content.as_bytes();
-    let mut x = 0;
+/// Lis une grille selon la grammaire décrite en tête de module
+pub fn parse(content: &str) -> Result<Grid, Error> {
+    read_lines(numbered_lines(content), &mut Silent)
+}
+
+/// Comme [`parse`], en tant qu'étape `parsing` de `progress`, qui avance à chaque ligne de la
+/// grille
+pub fn parse_with_progress(content: &str, progress: &mut dyn Progress) -> Result<Grid, Error> {
+    progress
+        .step("parsing", |progress| {
+            read_lines(numbered_lines(content), progress)
+        })
+        .0
+}
+
+/// Lignes numérotées à partir de 1; `lines` retire aussi le `\r` des fins de ligne Windows
+fn numbered_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line))
+}
+
+/// Comme [`parse`], mais à partir de lignes déjà découpées et numérotées (à partir de 1), pour
+/// que les erreurs indiquent leur position dans le fichier d'origine
+pub(crate) fn parse_lines<'a>(
+    lines: impl IntoIterator<Item = (usize, &'a str)>,
+) -> Result<Grid, Error> {
+    read_lines(lines, &mut Silent)
+}
+
+/// Lis les lignes numérotées d'une grille; seuls les évènements d'avancement sont envoyés à
+/// `progress`
+fn read_lines<'a>(
+    lines: impl IntoIterator<Item = (usize, &'a str)>,
+    progress: &mut dyn Progress,
+) -> Result<Grid, Error> {
+    let mut lines = lines
+        .into_iter()
+        .filter(|(_, line)| !line.chars().all(is_separator));
+
+    let (_, size) = lines.next().ok_or(Error::MissingSize)?;
+    let size = match size.trim().parse::<usize>() {
+        Ok(size) if size != 0 && size % 2 == 0 => size,
+        _ => return Err(Error::InvalidSize(size.trim().into())),
+    };
+
+    let mut grid = Grid::new(size);
     let mut y = 0;
-    let mut s = false;
 
-    for &element in bytes.iter() {
-        if element == b'\n' {
-            if !s {
-                s = true;
+    for (line_no, line) in lines {
+        let mut x = 0;
+
+        for (column, c) in line.chars().enumerate() {
+            if is_separator(c) {
+                continue;
             }
-        } else if s {
-            if element == b'1' {
-                grid.set(x, y, true);
-            } else if element == b'0' {
-                grid.set(x, y, false);
+
+            let cell = parse_cell(c).ok_or(Error::InvalidChar {
+                line: line_no,
+                column: column + 1,
+                found: c,
+            })?;
+
+            if x < size && y < size {
+                if let Cell::Filled(value) = cell {
+                    grid.set(x, y, value);
+                }
             }
             x += 1;
-            if x == grid.size {
-                y += 1;
-                x = 0;
-            }
         }
+
+        if x != size {
+            return Err(Error::RowLength {
+                line: line_no,
+                expected: size,
+                found: x,
+            });
+        }
+        y += 1;
+        progress.event(Event::Advance {
+            step: "parsing",
+            done: y.min(size),
+            total: size,
+        });
+    }
+
+    if y != size {
+        return Err(Error::RowCount {
+            expected: size,
+            found: y,
+        });
+    }
+
+    Ok(grid)
+}
+
+/// Écrit la grille sous sa forme canonique (voir l'en-tête du module)
+pub fn format(grid: &Grid) -> String {
+    let mut out = format!("{}\n", grid.size());
+
+    for y in 0..grid.size() {
+        out.extend((0..grid.size()).map(|x| match grid.get(x, y) {
+            Cell::Filled(true) => '1',
+            Cell::Filled(false) => '0',
+            Cell::Empty => '.',
+        }));
+        out.push('\n');
     }
+
+    out
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Cell;
+    use crate::progress::JsonLines;
 
     const BASIC: &str = "2\n01\n11\n";
 
     #[test]
     fn basic() {
-        let grid = {
-            let mut grid = Grid::new(size(BASIC).parse().unwrap());
-            fill_grid_from_file(&mut grid, BASIC);
-            grid
-        };
+        let grid = parse(BASIC).unwrap();
 
-        assert_eq!(grid.size, 2);
+        assert_eq!(grid.size(), 2);
         assert_eq!(grid.get(0, 0), Cell::Filled(false));
         assert_eq!(grid.get(0, 1), Cell::Filled(true));
         assert_eq!(grid.get(1, 0), Cell::Filled(true));
         assert_eq!(grid.get(1, 1), Cell::Filled(true));
     }
+
+    #[test]
+    fn empty_cells_and_separators() {
+        const FILE: &str = "4\r\n.1#0\r\n\r\n_ - 0 .\r\n|.|0|.|.|\r\n1,1,.,0\r\n";
+
+        let grid = parse(FILE).unwrap();
+
+        assert_eq!(format(&grid), "4\n.1.0\n..0.\n.0..\n11.0\n");
+    }
+
+    #[test]
+    fn canonical_round_trip() {
+        const FILE: &str = "4\n.1.0\n..0.\n.0..\n11.0\n";
+
+        assert_eq!(format(&parse(FILE).unwrap()), FILE);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("").unwrap_err(), Error::MissingSize);
+        assert_eq!(
+            parse("3\n...\n...\n...\n").unwrap_err(),
+            Error::InvalidSize("3".into()),
+        );
+        assert_eq!(
+            parse("2\n0x\n11\n").unwrap_err(),
+            Error::InvalidChar {
+                line: 2,
+                column: 2,
+                found: 'x',
+            },
+        );
+        assert_eq!(
+            parse("2\n011\n11\n").unwrap_err(),
+            Error::RowLength {
+                line: 2,
+                expected: 2,
+                found: 3,
+            },
+        );
+        assert_eq!(
+            parse("2\n01\n").unwrap_err(),
+            Error::RowCount {
+                expected: 2,
+                found: 1,
+            },
+        );
+    }
+
+    #[test]
+    fn progress() {
+        let mut json = JsonLines(Vec::new());
+        parse_with_progress(BASIC, &mut json).unwrap();
+        let events: Vec<serde_json::Value> = String::from_utf8(json.0)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let kinds: Vec<_> = events.iter().map(|event| &event["event"]).collect();
+        assert_eq!(kinds, ["start", "advance", "advance", "finish"]);
+        assert!(events.iter().all(|event| event["step"] == "parsing"));
+        assert_eq!(events[2]["done"], 2);
+        assert_eq!(events[2]["total"], 2);
+    }
 }
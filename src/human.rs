@@ -0,0 +1,269 @@
+//! Déductions « à la main », celles qu'un joueur applique sans chercher: elles servent à donner des
+//! indices explicables, là où le solveur SAT ne donne qu'une réponse
+use crate::bitboard::{self, Bitboard};
+use crate::rules::RuleSet;
+use crate::{Cell, Grid};
+use std::fmt::{Display, Formatter};
+
+/// Raison d'une déduction
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Reason {
+    /// L'autre valeur formerait trois cellules identiques consécutives (règle 2)
+    NoTriple,
+
+    /// La ligne contient déjà autant de cellules de l'autre valeur que permis (règle 1)
+    Balance,
+}
+
+/// Valeur imposée à une cellule vide
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Deduction {
+    pub x: usize,
+    pub y: usize,
+    pub value: bool,
+    pub reason: Reason,
+
+    /// La déduction vient de la colonne `x` plutôt que de la rangée `y`
+    pub column: bool,
+}
+
+impl Display for Deduction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let line = if self.column {
+            format!("la colonne {}", self.x)
+        } else {
+            format!("la rangée {}", self.y)
+        };
+        let other = !self.value as u8;
+
+        write!(f, "({}, {}) vaut {}: ", self.x, self.y, self.value as u8)?;
+        match self.reason {
+            Reason::NoTriple => write!(f, "sinon trois {other} se suivraient dans {line}"),
+            Reason::Balance => write!(f, "{line} contient déjà tous ses {other}"),
+        }
+    }
+}
+
+/// Première déduction applicable à `grid`, en cherchant d'abord les triplets puis l'équilibre
+pub fn next_deduction(grid: &Grid, rules: RuleSet) -> Option<Deduction> {
+    match Bitboard::new(grid) {
+        Some(board) => board_deduction(&board, rules),
+        None => cell_deduction(grid, rules),
+    }
+}
+
+/// [`next_deduction`], ligne par ligne sur les masques de bits
+fn board_deduction(board: &Bitboard, rules: RuleSet) -> Option<Deduction> {
+    let size = board.size();
+    let lines = || {
+        [false, true].iter().flat_map(move |&column| {
+            board
+                .lines(column)
+                .iter()
+                .enumerate()
+                .map(move |(k, &line)| (column, k, line))
+        })
+    };
+
+    if rules.no_triples {
+        for (column, k, line) in lines() {
+            // Bit i: parmi les cellules i, i + 1 et i + 2, deux identiques et une vide
+            let empty = line.empty(size);
+            let windows = [false, true].iter().fold(0, |windows, &value| {
+                let same = line.cells(value);
+                windows
+                    | (empty & same >> 1 & same >> 2)
+                    | (same & empty >> 1 & same >> 2)
+                    | (same & same >> 1 & empty >> 2)
+            });
+            if windows == 0 {
+                continue;
+            }
+
+            let start = windows.trailing_zeros() as usize;
+            let hole = start + (empty >> start).trailing_zeros() as usize;
+            let other = if hole == start { start + 1 } else { start };
+            let (x, y) = bitboard::position(k, hole, column);
+            return Some(Deduction {
+                x,
+                y,
+                value: line.ones >> other & 1 == 0,
+                reason: Reason::NoTriple,
+                column,
+            });
+        }
+    }
+
+    if rules.balance {
+        for (column, k, line) in lines() {
+            let empty = line.empty(size);
+            for value in [false, true] {
+                if line.count(value) != size / 2 || empty == 0 {
+                    continue;
+                }
+
+                let (x, y) = bitboard::position(k, empty.trailing_zeros() as usize, column);
+                return Some(Deduction {
+                    x,
+                    y,
+                    value: !value,
+                    reason: Reason::Balance,
+                    column,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// [`next_deduction`], cellule par cellule, pour les grilles trop grandes pour un [`Bitboard`]
+fn cell_deduction(grid: &Grid, rules: RuleSet) -> Option<Deduction> {
+    let size = grid.size();
+    let lines = || {
+        [false, true].iter().flat_map(move |&column| {
+            (0..size).map(move |k| {
+                let cells: Vec<_> = (0..size)
+                    .map(|i| if column { (k, i) } else { (i, k) })
+                    .collect();
+                (column, cells)
+            })
+        })
+    };
+
+    if rules.no_triples {
+        for (column, cells) in lines() {
+            for window in cells.windows(3) {
+                let values: Vec<_> = window.iter().map(|&(x, y)| grid.get(x, y)).collect();
+
+                // Parmi les trois cellules, deux identiques et une vide: la vide prend l'autre
+                // valeur
+                for empty in 0..3 {
+                    let others: Vec<_> =
+                        (0..3).filter(|&i| i != empty).map(|i| values[i]).collect();
+                    if let (Cell::Empty, Cell::Filled(value)) = (values[empty], others[0]) {
+                        if others[1] == others[0] {
+                            let (x, y) = window[empty];
+                            return Some(Deduction {
+                                x,
+                                y,
+                                value: !value,
+                                reason: Reason::NoTriple,
+                                column,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if rules.balance {
+        for (column, cells) in lines() {
+            for value in [false, true] {
+                let count = cells
+                    .iter()
+                    .filter(|&&(x, y)| grid.get(x, y) == Cell::Filled(value))
+                    .count();
+                if count != size / 2 {
+                    continue;
+                }
+
+                if let Some(&(x, y)) = cells.iter().find(|&&(x, y)| grid.get(x, y) == Cell::Empty) {
+                    return Some(Deduction {
+                        x,
+                        y,
+                        value: !value,
+                        reason: Reason::Balance,
+                        column,
+                    });
+                }
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{self, XorShift};
+    use crate::grid_read;
+
+    #[test]
+    fn no_triple() {
+        let grid = grid_read::parse("4\n....\n1...\n1...\n....\n").unwrap();
+
+        let deduction = next_deduction(&grid, RuleSet::TAKUZU).unwrap();
+
+        assert_eq!(
+            deduction,
+            Deduction {
+                x: 0,
+                y: 0,
+                value: false,
+                reason: Reason::NoTriple,
+                column: true,
+            },
+        );
+        assert_eq!(
+            deduction.to_string(),
+            "(0, 0) vaut 0: sinon trois 1 se suivraient dans la colonne 0",
+        );
+
+        // Cellule vide entre deux cellules identiques
+        let grid = grid_read::parse("4\n0.0.\n....\n....\n....\n").unwrap();
+        assert_eq!(
+            next_deduction(&grid, RuleSet::TAKUZU).map(|d| (d.x, d.y, d.value)),
+            Some((1, 0, true)),
+        );
+    }
+
+    #[test]
+    fn balance() {
+        let grid = grid_read::parse("4\n1..1\n....\n....\n....\n").unwrap();
+
+        let deduction = next_deduction(&grid, RuleSet::TAKUZU).unwrap();
+
+        // La règle 2 ne dit rien ici, seul l'équilibre s'applique
+        assert_eq!(deduction.reason, Reason::Balance);
+        assert_eq!((deduction.x, deduction.y, deduction.value), (1, 0, false));
+        assert_eq!(
+            deduction.to_string(),
+            "(1, 0) vaut 0: la rangée 0 contient déjà tous ses 1",
+        );
+    }
+
+    /// Les masques de bits trouvent la même première déduction que le parcours cellule par cellule
+    #[test]
+    fn board_matches_cells() {
+        let mut rng = XorShift::new(11);
+        for size in [2, 4, 6, 8, 10] {
+            for _ in 0..300 {
+                let grid = generate::random_partial_grid(size, &mut rng);
+
+                let board = Bitboard::new(&grid).unwrap();
+                assert_eq!(
+                    board_deduction(&board, RuleSet::TAKUZU),
+                    cell_deduction(&grid, RuleSet::TAKUZU),
+                    "{}",
+                    grid_read::format(&grid)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn stuck() {
+        assert_eq!(next_deduction(&Grid::new(4), RuleSet::TAKUZU), None);
+
+        let grid = grid_read::parse("4\n11..\n....\n....\n....\n").unwrap();
+        let rules = RuleSet {
+            no_triples: false,
+            balance: false,
+            ..RuleSet::TAKUZU
+        };
+        assert_eq!(next_deduction(&grid, rules), None);
+    }
+}
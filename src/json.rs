@@ -0,0 +1,170 @@
+//! Représentation JSON des grilles et des résultats de résolution.
+//!
+//! Une cellule vaut `0`, `1` ou `null` (vide); une grille est un objet
+//! `{"size": 4, "cells": [[null, 1, null, 0], ...]}` dont `cells` contient une liste par rangée.
+
+use crate::solve::{Status, Timings};
+use crate::{Cell, Grid};
+use serde::de::{self, Deserializer, Unexpected};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+impl Serialize for Cell {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Cell::Filled(value) => serializer.serialize_u8(*value as u8),
+            Cell::Empty => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Cell {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Option::<u64>::deserialize(deserializer)? {
+            Some(0) => Ok(Cell::Filled(false)),
+            Some(1) => Ok(Cell::Filled(true)),
+            Some(n) => Err(de::Error::invalid_value(
+                Unexpected::Unsigned(n),
+                &"0, 1 ou null",
+            )),
+            None => Ok(Cell::Empty),
+        }
+    }
+}
+
+/// Forme sérialisée d'une [`Grid`], validée à la désérialisation
+#[derive(Deserialize, Serialize)]
+struct GridRepr {
+    size: usize,
+    cells: Vec<Vec<Cell>>,
+}
+
+impl From<&Grid> for GridRepr {
+    fn from(grid: &Grid) -> Self {
+        GridRepr {
+            size: grid.size(),
+            cells: grid
+                .inner()
+                .chunks(grid.size())
+                .map(<[_]>::to_vec)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<GridRepr> for Grid {
+    type Error = String;
+
+    fn try_from(repr: GridRepr) -> Result<Self, Self::Error> {
+        let GridRepr { size, cells } = repr;
+
+        if size == 0 || size % 2 != 0 {
+            return Err(format!("taille incorrecte: {size}"));
+        }
+        if cells.len() != size {
+            return Err(format!("{} rangées au lieu de {size}", cells.len()));
+        }
+        if let Some((y, row)) = cells.iter().enumerate().find(|(_, row)| row.len() != size) {
+            return Err(format!(
+                "rangée {y}: {} cellules au lieu de {size}",
+                row.len(),
+            ));
+        }
+
+        Ok(Grid {
+            size,
+            inner: cells.into_iter().flatten().collect(),
+        })
+    }
+}
+
+impl Serialize for Grid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GridRepr::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Grid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Grid::try_from(GridRepr::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// Indique si `content` ressemble à un document JSON plutôt qu'à un fichier `.grd`
+pub fn is_json(content: &str) -> bool {
+    content.trim_start().starts_with('{')
+}
+
+pub fn parse_grid(content: &str) -> Result<Grid, serde_json::Error> {
+    serde_json::from_str(content)
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonTimings {
+    pub encoding_ms: f64,
+    pub solving_ms: f64,
+}
+
+impl From<Timings> for JsonTimings {
+    fn from(timings: Timings) -> Self {
+        JsonTimings {
+            encoding_ms: timings.encoding.as_secs_f64() * 1000.0,
+            solving_ms: timings.solving.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Résultat d'une résolution, tel qu'écrit par `--format json`
+#[derive(Debug, Serialize)]
+pub struct SolveReport {
+    pub size: Option<usize>,
+    pub givens: Option<Grid>,
+    pub solution: Option<Grid>,
+    pub status: Status,
+    pub timings: Option<JsonTimings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SolveReport {
+    /// Écrit le rapport sur une seule ligne
+    pub fn write(&self, mut w: impl Write) -> io::Result<()> {
+        serde_json::to_writer(&mut w, self)?;
+        writeln!(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_read;
+
+    #[test]
+    fn grid_round_trip() {
+        let grid = grid_read::parse("4\n.1.0\n..0.\n.0..\n11.0\n").unwrap();
+
+        let json = serde_json::to_string(&grid).unwrap();
+        assert_eq!(
+            json,
+            r#"{"size":4,"cells":[[null,1,null,0],[null,null,0,null],[null,0,null,null],[1,1,null,0]]}"#,
+        );
+
+        assert_eq!(parse_grid(&json).unwrap().inner(), grid.inner());
+    }
+
+    #[test]
+    fn invalid_grids() {
+        assert!(parse_grid(r#"{"size":3,"cells":[]}"#).is_err());
+        assert!(parse_grid(r#"{"size":2,"cells":[[0,1]]}"#).is_err());
+        assert!(parse_grid(r#"{"size":2,"cells":[[0,1],[1]]}"#).is_err());
+        assert!(parse_grid(r#"{"size":2,"cells":[[0,1],[1,2]]}"#).is_err());
+    }
+
+    #[test]
+    fn detection() {
+        assert!(is_json("  \n{\"size\": 2}"));
+        assert!(!is_json("2\n..\n..\n"));
+    }
+}
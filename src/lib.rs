@@ -0,0 +1,182 @@
+//! Solveur de Takuzu basé sur un SAT-solveur.
+//!
+//! La bibliothèque couvre toute la chaîne de résolution:
+//!   - construction et lecture des grilles: [`Grid`], [`grid_read`], [`json`], [`collection`],
+//!     [`tatham`], [`pzprjs`]; grilles aléatoires: [`generate`];
+//!   - encodage des règles en CNF: [`rules::encode`] et [`EncodingOptions`], ou [`cnf::CNFFile`]
+//!     pour écrire un fichier DIMACS; taille de la formule prévue, avec des limites:
+//!     [`estimate`], ou obtenue: [`stats`];
+//!   - résolution: [`solve`], avec le solveur choisi dans [`backend`] (dont les solveurs externes
+//!     de [`external`]), ou sans SAT-solveur: [`native`];
+//!   - décodage des résultats d'un solveur: [`sat`]; comparaison des chemins de résolution:
+//!     [`crosscheck`];
+//!   - vérification directe des règles et déductions simples: [`validate`], [`human`], sur des
+//!     masques de bits: [`bitboard`];
+//!   - vérification exhaustive des encodages sur les petites grilles: [`verify`];
+//!   - rendu: [`svg`], [`tikz`];
+//!   - suivi de l'avancement des étapes longues: [`progress`].
+//!
+//! ```
+//! use inf402_takuzu::solve::{self, Outcome};
+//! use inf402_takuzu::grid_read;
+//!
+//! let grid = grid_read::parse("4\n.1.0\n..0.\n.0..\n11.0\n").unwrap();
+//! match solve::solve(&grid).unwrap().0 {
+//!     Outcome::Solved(solution) => assert_eq!(solution.size(), 4),
+//!     outcome => panic!("{:?}", outcome),
+//! }
+//! ```
+
+pub mod backend;
+pub mod bitboard;
+pub mod cnf;
+pub mod collection;
+pub mod crosscheck;
+pub mod estimate;
+pub mod external;
+pub mod generate;
+pub mod grid_read;
+pub mod human;
+pub mod json;
+pub mod logic_utils;
+pub mod native;
+pub mod progress;
+pub mod pzprjs;
+pub mod rules;
+pub mod sat;
+pub mod solve;
+pub mod stats;
+pub mod svg;
+pub mod tatham;
+pub mod tikz;
+pub mod validate;
+pub mod verify;
+
+pub use crate::rules::{EncodingOptions, RuleSet};
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+/// Cellule d'une grille: vide, ou remplie par un 0 (`false`) ou un 1 (`true`)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Cell {
+    Filled(bool),
+    Empty,
+}
+
+/// Grille carrée de taille paire; la cellule `(x, y)` est à l'indice `y * size + x`
+#[derive(Clone, Debug)]
+pub struct Grid {
+    size: usize,
+    inner: Vec<Cell>,
+}
+
+impl Grid {
+    pub fn new(size: usize) -> Self {
+        assert_ne!(size, 0);
+        assert_eq!(size % 2, 0);
+
+        Grid {
+            size,
+            inner: vec![Cell::Empty; size * size],
+        }
+    }
+
+    /// Nombre de cellules d'une rangée ou d'une colonne
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Cellules, rangée par rangée
+    pub fn inner(&self) -> &[Cell] {
+        &self.inner
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cell {
+        let i = y * self.size + x;
+        self.inner[i]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: bool) {
+        let i = y * self.size + x;
+        self.inner[i] = Cell::Filled(value);
+    }
+
+    /// Comme [`Grid::set`], mais permet aussi de vider la cellule
+    pub fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        let i = y * self.size + x;
+        self.inner[i] = cell;
+    }
+
+    pub fn print(&self) {
+        self.write(std::io::stdout().lock()).unwrap();
+    }
+
+    /// Écrit les rangées de la grille, une par ligne, comme [`Grid::print`]
+    pub fn write(&self, mut w: impl Write) -> io::Result<()> {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let c = self.get(x, y);
+                match c {
+                    Cell::Filled(true) => write!(w, "1")?,
+                    Cell::Filled(false) => write!(w, "0")?,
+                    Cell::Empty => write!(w, ".")?,
+                }
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+}
+
+/// Construit une grille à partir d'une liste de cellules, de façon safe (vérification de la taille)
+impl TryFrom<Vec<Cell>> for Grid {
+    type Error = ();
+
+    fn try_from(value: Vec<Cell>) -> Result<Self, Self::Error> {
+        /// Racine carrée entière "stricte", renvoie `None` si le nombre n'en a pas
+        fn int_sqrt(n: usize) -> Option<usize> {
+            match n {
+                // Les premiers cas sont là pour des raisons de performance
+                4 => Some(2),
+                16 => Some(4),
+                36 => Some(6),
+                64 => Some(8),
+                // Cas général, recourt à des flottants
+                n => {
+                    let sqrt = (n as f64).sqrt() as usize;
+                    if sqrt.pow(2) == n {
+                        Some(sqrt)
+                    } else {
+                        None
+                    }
+                }
+            }
+        }
+
+        if let Some(size) = int_sqrt(value.len()) {
+            Ok(Grid { size, inner: value })
+        } else {
+            Err(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_get_set() {
+        let mut g = Grid::new(6);
+        g.set(0, 1, true);
+        g.set(0, 0, false);
+        assert_eq!(g.get(0, 1), Cell::Filled(true));
+        assert_eq!(g.get(0, 0), Cell::Filled(false));
+        assert_eq!(g.get(0, 0), Cell::Filled(false));
+
+        g.set_cell(0, 1, Cell::Empty);
+        assert_eq!(g.get(0, 1), Cell::Empty);
+        assert_eq!(g.size(), 6);
+        assert_eq!(g.inner().len(), 36);
+    }
+}
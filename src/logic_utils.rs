@@ -1,3 +1,4 @@
+//! Conversion de formules logiques
 use std::collections::HashSet;
 use std::hash::Hash;
 use std::iter::FromIterator;
@@ -54,7 +55,7 @@ mod tests {
 
         assert_eq!(cnf.len(), EXPECTED_CNF.len());
         for clause in EXPECTED_CNF {
-            assert!(cnf.contains(&HashSet::from_iter(clause.into_iter().copied())))
+            assert!(cnf.contains(&HashSet::from_iter(clause.iter().copied())))
         }
     }
 
@@ -73,7 +74,7 @@ mod tests {
 
         assert_eq!(cnf.len(), EXPECTED_CNF.len());
         for clause in EXPECTED_CNF {
-            assert!(cnf.contains(&HashSet::from_iter(clause.into_iter().copied())))
+            assert!(cnf.contains(&HashSet::from_iter(clause.iter().copied())))
         }
     }
 
@@ -1,213 +1,1437 @@
-mod cnf;
-mod grid_read;
-mod logic_utils;
-mod rules;
-mod sat;
-
-use crate::cnf::CNFFile;
-use std::convert::TryFrom;
-use std::env;
+mod batch;
+mod bench;
+mod play;
+mod repl;
+mod term;
+
+use crate::term::TermStyle;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use inf402_takuzu::backend::{Backend, VarisatBackend};
+use inf402_takuzu::cnf::CNFFile;
+use inf402_takuzu::crosscheck::{self, DimacsSolver};
+use inf402_takuzu::estimate::{self, Limits};
+use inf402_takuzu::json::{self, SolveReport};
+use inf402_takuzu::progress::{self, Event, Progress, Silent};
+use inf402_takuzu::rules::{self, BalanceEncoding, EncodingOptions, RuleSet, UniquenessEncoding};
+use inf402_takuzu::solve::{self, Outcome, Status, Timings};
+use inf402_takuzu::{bitboard, grid_read, native, pzprjs, sat, stats, svg, tatham, tikz, Grid};
 use std::fs::File;
-use std::io::BufWriter;
-use std::path::PathBuf;
-use std::time::Instant;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Niveau de détail des messages sur la sortie d'erreur: 0 avec `--quiet`, 2 avec `--verbose`
+static VERBOSITY: AtomicU8 = AtomicU8::new(1);
+
+/// Affichage de l'avancement choisi par `--progress`
+static PROGRESS: OnceLock<ProgressFormat> = OnceLock::new();
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub enum Cell {
-    Filled(bool),
-    Empty,
+/// Compte la mémoire allouée, pour le mode `bench` (feature `peak-memory`)
+#[cfg(feature = "peak-memory")]
+#[global_allocator]
+static ALLOCATOR: bench::PeakAlloc = bench::PeakAlloc;
+
+/// Message d'information, masqué par `--quiet`
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if VERBOSITY.load(Ordering::Relaxed) >= 1 {
+            reporter().event(Event::Message { text: &format!($($arg)*) });
+        }
+    };
 }
 
-#[derive(Clone, Debug)]
-pub struct Grid {
-    size: usize,
-    inner: Vec<Cell>,
+/// Message affiché seulement avec `--verbose`
+macro_rules! detail {
+    ($($arg:tt)*) => {
+        if VERBOSITY.load(Ordering::Relaxed) >= 2 {
+            reporter().event(Event::Message { text: &format!($($arg)*) });
+        }
+    };
 }
 
-impl Grid {
-    pub fn new(size: usize) -> Self {
-        assert_ne!(size, 0);
-        assert_eq!(size % 2, 0);
+/// Destinataire des évènements d'avancement et des messages, sur la sortie d'erreur; rien avec
+/// `--quiet`
+fn reporter() -> Box<dyn Progress> {
+    if VERBOSITY.load(Ordering::Relaxed) == 0 {
+        return Box::new(Silent);
+    }
 
-        Grid {
-            size,
-            inner: vec![Cell::Empty; size * size],
+    match PROGRESS.get().copied().unwrap_or(ProgressFormat::Auto) {
+        ProgressFormat::Auto if io::stderr().is_terminal() => {
+            Box::new(progress::Bar::new(io::stderr()))
         }
+        ProgressFormat::Auto | ProgressFormat::Text => Box::new(progress::Text(io::stderr())),
+        ProgressFormat::Bar => Box::new(progress::Bar::new(io::stderr())),
+        ProgressFormat::Json => Box::new(progress::JsonLines(io::stderr())),
     }
+}
+
+/// Solveur de Takuzu basé sur un SAT-solveur
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// N'affiche que les avertissements et les erreurs
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Affiche aussi la taille de la formule et la durée de chaque étape
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Affichage de l'avancement sur la sortie d'erreur
+    #[arg(long, global = true, value_enum, default_value_t = ProgressFormat::Auto)]
+    progress: ProgressFormat,
+
+    /// Nombre de threads qui génèrent la formule (par défaut, un par cœur); la formule ne dépend
+    /// pas de ce nombre
+    #[arg(long, global = true, value_name = "N")]
+    threads: Option<usize>,
+
+    #[command(subcommand)]
+    mode: Mode,
+}
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Écrit la formule de la grille au format DIMACS, pour un SAT-solveur externe
+    Cnf {
+        /// Fichier de grille (`-` pour l'entrée standard)
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
+
+        #[command(flatten)]
+        encoding: EncodingArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Lis le résultat d'un SAT-solveur et affiche la grille correspondante
+    Sol {
+        /// Fichier de résultats (`-` pour l'entrée standard)
+        #[arg(value_name = "RÉSULTAT")]
+        result: PathBuf,
+
+        /// Taille de la grille, par défaut la plus petite taille paire qui contient toutes les
+        /// variables du modèle (au plus 64); nécessaire si la formule a des variables auxiliaires
+        #[arg(value_name = "TAILLE")]
+        size: Option<usize>,
+
+        /// Formule DIMACS écrite par `cnf`, qui indique la taille de la grille
+        #[arg(long, value_name = "FORMULE", conflicts_with = "size")]
+        formula: Option<PathBuf>,
+
+        /// Format de la sortie
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Résoud la grille et vérifie l'unicité de la solution
+    #[command(alias = "varisat")]
+    Solve {
+        /// Fichier de grille (`-` pour l'entrée standard)
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
+
+        #[command(flatten)]
+        encoding: EncodingArgs,
+
+        #[command(flatten)]
+        solver: SolverArgs,
+
+        /// Format de la sortie
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Résoud la grille sans SAT-solveur: propagation des règles et retour sur trace
+    Native {
+        /// Fichier de grille (`-` pour l'entrée standard)
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
+
+        /// Règles imposées, comme pour `cnf`
+        #[arg(long, default_value = "takuzu", value_parser = parse_with::<RuleSet>)]
+        rules: RuleSet,
+
+        /// Format de la sortie
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Mesure la formule de la grille, règle par règle: clauses, littéraux, longueurs, clauses
+    /// écartées par les cellules données, variables auxiliaires, durée et taille DIMACS
+    Stats {
+        /// Fichier de grille (`-` pour l'entrée standard)
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
+
+        #[command(flatten)]
+        encoding: EncodingArgs,
+
+        /// Estime seulement la taille de la formule, sans la générer (majorant, sans tenir compte
+        /// des cellules données)
+        #[arg(long)]
+        estimate: bool,
+
+        /// Format de la sortie
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Réécrit la grille sous forme canonique
+    Fmt {
+        /// Fichier de grille (`-` pour l'entrée standard)
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Résoud toutes les grilles d'une collection, ou de toutes les collections d'un dossier
+    Batch {
+        #[arg(value_name = "COLLECTION|DOSSIER")]
+        path: PathBuf,
+
+        #[command(flatten)]
+        encoding: EncodingArgs,
+
+        #[command(flatten)]
+        solver: SolverArgs,
+
+        /// Format de la sortie
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Mesure les durées, la taille de la formule et la mémoire pour chaque combinaison
+    /// d'encodages et chaque solveur, sur des grilles générées ou lues
+    Bench {
+        #[command(flatten)]
+        puzzles: PuzzleArgs,
+
+        /// Règles encodées, comme pour `cnf`
+        #[arg(long, default_value = "takuzu", value_parser = parse_with::<RuleSet>)]
+        rules: RuleSet,
+
+        #[command(flatten)]
+        limits: LimitArgs,
+
+        /// Solveur intégré, répétable; par défaut, tous ceux qui ont été compilés
+        #[arg(long = "backend", value_name = "BACKEND", value_parser = parse_with::<Backend>)]
+        backends: Vec<Backend>,
+
+        /// Commande d'un SAT-solveur externe, répétable
+        #[arg(long = "solver", value_name = "COMMANDE")]
+        solvers: Vec<String>,
+
+        /// Durée maximale de chaque appel aux solveurs externes, en secondes
+        #[arg(long, value_name = "SECONDES", requires = "solvers", value_parser = parse_timeout)]
+        timeout: Option<Duration>,
+
+        /// Format de la sortie
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Résoud des grilles par la formule `varisat`, par le fichier DIMACS et par le solveur
+    /// natif, et vérifie que les trois s'accordent
+    Crosscheck {
+        #[command(flatten)]
+        puzzles: PuzzleArgs,
+
+        #[command(flatten)]
+        encoding: EncodingArgs,
+
+        /// Commande du SAT-solveur externe qui résout le fichier DIMACS (par défaut, `varisat`
+        /// relit le fichier)
+        #[arg(long, value_name = "COMMANDE")]
+        solver: Option<String>,
+
+        /// Durée maximale de chaque appel au solveur externe, en secondes
+        #[arg(long, value_name = "SECONDES", requires = "solver", value_parser = parse_timeout)]
+        timeout: Option<Duration>,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Partie interactive dans le terminal, sauvegardée à côté de la grille (extension `.sav`)
+    Play {
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
+
+        #[command(flatten)]
+        encoding: EncodingArgs,
+    },
+
+    /// Session interactive en ligne de commande sur la grille
+    Repl {
+        /// Fichier de grille (`-` pour l'entrée standard, seulement avec un fichier de commandes)
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
+
+        /// Fichier de commandes, recopiées dans la sortie (sinon, l'entrée standard)
+        script: Option<PathBuf>,
+
+        #[command(flatten)]
+        encoding: EncodingArgs,
+
+        #[command(flatten)]
+        solver: SolverArgs,
+    },
+
+    /// Dessine la grille en SVG
+    Svg {
+        /// Fichier de grille (`-` pour l'entrée standard)
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
+
+        /// Dessine aussi la solution
+        #[arg(long)]
+        solution: bool,
+
+        /// Côté d'une cellule, en pixels
+        #[arg(long, value_name = "PIXELS", default_value_t = svg::SvgOptions::default().cell_size)]
+        cell_size: u32,
+
+        /// Numérote les colonnes et les rangées à partir de 0, comme les coordonnées `(x, y)`
+        #[arg(long)]
+        coordinates: bool,
+
+        /// Indique sous la grille les règles choisies par `--rules`
+        #[arg(long)]
+        show_rules: bool,
+
+        #[command(flatten)]
+        encoding: EncodingArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Exporte la grille en LaTeX: image TikZ autonome, ou tableau des statistiques de la formule
+    Tikz {
+        /// Fichier de grille (`-` pour l'entrée standard)
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
 
-    pub fn get(&self, x: usize, y: usize) -> Cell {
-        let i = y * self.size + x;
-        self.inner[i]
+        #[arg(value_enum, default_value_t = TikzContent::Puzzle)]
+        content: TikzContent,
+
+        #[command(flatten)]
+        encoding: EncodingArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Importe une grille depuis un identifiant Unruly ou une URL pzprjs
+    Import {
+        #[arg(value_name = "IDENTIFIANT|URL")]
+        id: String,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Exporte une grille vers un identifiant externe
+    Export {
+        #[arg(value_enum)]
+        target: ExportTarget,
+
+        /// Fichier de grille (`-` pour l'entrée standard)
+        #[arg(value_name = "GRILLE")]
+        grid: PathBuf,
+
+        /// Règles de la grille, gardées dans l'identifiant Tatham (`takuzu` ou `unruly`)
+        #[arg(long, default_value = "takuzu", value_parser = parse_with::<RuleSet>)]
+        rules: RuleSet,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+/// Format de sortie des rapports
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    /// Seulement pour `batch` et `bench`
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum ProgressFormat {
+    /// Barre de progression si la sortie d'erreur est un terminal, sinon `text`
+    Auto,
+    /// Barre de progression redessinée sur place
+    Bar,
+    /// Une ligne par étape
+    Text,
+    /// Un objet JSON par ligne et par évènement, pour l'intégration continue
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum TikzContent {
+    /// La grille seule
+    Puzzle,
+    /// La solution seule
+    Solution,
+    /// La grille et sa solution côte à côte
+    Both,
+    /// Le tableau des statistiques de la formule
+    Stats,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum ExportTarget {
+    /// Identifiant du jeu Unruly de Simon Tatham
+    Tatham,
+    /// URL de puzz.link (pzprjs)
+    Pzprjs,
+}
+
+/// Analyse la valeur d'une option avec le `FromStr` du type de la bibliothèque
+fn parse_with<T: FromStr<Err = ()>>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|()| format!("valeur inconnue: {s:?}"))
+}
+
+fn parse_timeout(s: &str) -> Result<Duration, String> {
+    s.parse()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("délai invalide: {s:?}"))
+}
+
+fn parse_size(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(size) if size > 0 && size % 2 == 0 => Ok(size),
+        _ => Err(format!("taille invalide (entier pair attendu): {s:?}")),
     }
+}
 
-    pub fn set(&mut self, x: usize, y: usize, value: bool) {
-        let i = y * self.size + x;
-        self.inner[i] = Cell::Filled(value);
+fn parse_density(s: &str) -> Result<f64, String> {
+    match s.parse() {
+        Ok(density) if (0.0..=1.0).contains(&density) => Ok(density),
+        _ => Err(format!("proportion invalide (entre 0 et 1): {s:?}")),
     }
+}
 
-    pub fn print(&self) {
-        for y in 0..self.size {
-            for x in 0..self.size {
-                let c = self.get(x, y);
-                match c {
-                    Cell::Filled(true) => print!("1"),
-                    Cell::Filled(false) => print!("0"),
-                    Cell::Empty => print!("."),
-                }
+#[derive(Args)]
+struct EncodingArgs {
+    /// Règles encodées: `takuzu`, `unruly` (sans l'unicité des lignes), ou liste de numéros,
+    /// par exemple `1,2`
+    #[arg(long, default_value = "takuzu", value_parser = parse_with::<RuleSet>)]
+    rules: RuleSet,
+
+    /// Encodage de la règle 1: `combinations`, ou `sequential` (compteurs, avec des variables
+    /// auxiliaires)
+    #[arg(long, default_value = "combinations", value_parser = parse_with::<BalanceEncoding>)]
+    balance: BalanceEncoding,
+
+    /// Encodage de la règle 3: `expansion`, ou `tseitin` (avec des variables auxiliaires)
+    #[arg(long, default_value = "expansion", value_parser = parse_with::<UniquenessEncoding>)]
+    uniqueness: UniquenessEncoding,
+
+    #[command(flatten)]
+    limits: LimitArgs,
+}
+
+impl EncodingArgs {
+    fn options(&self) -> EncodingOptions {
+        EncodingOptions {
+            rules: self.rules,
+            balance: self.balance,
+            uniqueness: self.uniqueness,
+        }
+    }
+
+    fn limits(&self) -> Limits {
+        self.limits.limits()
+    }
+}
+
+#[derive(Args)]
+struct LimitArgs {
+    /// Nombre maximal de clauses, estimé avant de générer la formule
+    #[arg(long, value_name = "N", default_value_t = Limits::default().max_clauses)]
+    max_clauses: u128,
+
+    /// Mémoire maximale pour générer la formule, estimée à l'avance, en Mio
+    #[arg(long, value_name = "MIO", default_value_t = Limits::default().max_memory_bytes >> 20)]
+    max_memory: u128,
+
+    /// Génère la formule quelle que soit sa taille
+    #[arg(long, conflicts_with_all = ["max_clauses", "max_memory"])]
+    no_limit: bool,
+}
+
+impl LimitArgs {
+    fn limits(&self) -> Limits {
+        if self.no_limit {
+            Limits::NONE
+        } else {
+            Limits {
+                max_clauses: self.max_clauses,
+                max_memory_bytes: self.max_memory.saturating_mul(1 << 20),
             }
-            println!();
-        }
-    }
-}
-
-/// Construit une grille à partir d'une liste de cellules, de façon safe (vérification de la taille)
-impl TryFrom<Vec<Cell>> for Grid {
-    type Error = ();
-
-    fn try_from(value: Vec<Cell>) -> Result<Self, Self::Error> {
-        /// Racine carrée entière "stricte", renvoie `None` si le nombre n'en a pas
-        fn int_sqrt(n: usize) -> Option<usize> {
-            match n {
-                // Les premiers cas sont là pour des raisons de performance
-                4 => Some(2),
-                16 => Some(4),
-                36 => Some(6),
-                64 => Some(8),
-                // Cas général, recourt à des flottants
-                n => {
-                    let sqrt = (n as f64).sqrt() as usize;
-                    if sqrt.pow(2) == n {
-                        Some(sqrt)
-                    } else {
-                        None
-                    }
+        }
+    }
+}
+
+/// Grilles de `bench` et `crosscheck`: générées, ou lues avec `--puzzles`
+#[derive(Args)]
+struct PuzzleArgs {
+    /// Tailles des grilles générées [défaut: 4,6,8,10,12,14,16,20,24,30 pour `bench`, 4,6,8 pour
+    /// `crosscheck`]
+    #[arg(
+        long,
+        value_name = "TAILLES",
+        value_delimiter = ',',
+        value_parser = parse_size
+    )]
+    sizes: Vec<usize>,
+
+    /// Nombre de grilles générées par taille [défaut: 3 pour `bench`, 10 pour `crosscheck`]
+    #[arg(long)]
+    count: Option<usize>,
+
+    /// Graine du générateur: les mêmes options donnent les mêmes grilles
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Proportion de cellules données dans les grilles générées
+    #[arg(long, default_value_t = 0.4, value_parser = parse_density)]
+    density: f64,
+
+    /// Grilles d'une collection, ou de toutes les collections d'un dossier, au lieu d'en générer
+    #[arg(
+        long,
+        value_name = "COLLECTION|DOSSIER",
+        conflicts_with_all = ["sizes", "count", "seed", "density"]
+    )]
+    puzzles: Option<PathBuf>,
+}
+
+impl PuzzleArgs {
+    /// Grilles choisies, avec les tailles et le nombre par taille du mode quand ils ne sont pas
+    /// donnés
+    fn source(self, sizes: &[usize], count: usize) -> PuzzleSource {
+        match self.puzzles {
+            Some(path) => PuzzleSource::Files(path),
+            None => PuzzleSource::Generated(
+                if self.sizes.is_empty() {
+                    sizes.to_vec()
+                } else {
+                    self.sizes
+                },
+                self.count.unwrap_or(count),
+                self.density,
+                self.seed,
+            ),
+        }
+    }
+}
+
+#[derive(Args)]
+struct SolverArgs {
+    /// Solveur intégré: `varisat`, ou `splr` et `batsat` s'ils ont été compilés
+    #[arg(long, default_value = "varisat", value_parser = parse_with::<Backend>)]
+    backend: Backend,
+
+    /// Commande d'un SAT-solveur externe, à la place du solveur intégré
+    #[arg(long, value_name = "COMMANDE", conflicts_with = "backend")]
+    solver: Option<String>,
+
+    /// Durée maximale de chaque appel au solveur externe, en secondes
+    #[arg(long, value_name = "SECONDES", requires = "solver", value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+}
+
+impl From<SolverArgs> for Backend {
+    fn from(args: SolverArgs) -> Self {
+        match args.solver {
+            Some(command) => Backend::External {
+                command,
+                timeout: args.timeout,
+            },
+            None => args.backend,
+        }
+    }
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// Fichier de sortie (par défaut, ou avec `-`, la sortie standard)
+    #[arg(short, long, value_name = "FICHIER")]
+    output: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    fn is_stdout(&self) -> bool {
+        self.output
+            .as_ref()
+            .is_none_or(|path| path == Path::new("-"))
+    }
+
+    /// Ouvre la sortie, ou quitte le programme avec un message d'erreur
+    fn open(&self) -> Box<dyn Write> {
+        match &self.output {
+            Some(path) if !self.is_stdout() => match File::create(path) {
+                Ok(file) => Box::new(BufWriter::new(file)),
+                Err(err) => {
+                    eprintln!("impossible de créer {path:?}: {err}");
+                    std::process::exit(1);
                 }
-            }
+            },
+            _ => Box::new(io::stdout().lock()),
         }
+    }
 
-        if let Some(size) = int_sqrt(value.len()) {
-            Ok(Grid { size, inner: value })
+    /// Style du rendu: les couleurs et le cadre ne sont utilisés que vers un terminal
+    fn style(&self) -> TermStyle {
+        if self.is_stdout() {
+            TermStyle::detect()
         } else {
-            Err(())
+            TermStyle::PLAIN
         }
     }
 }
 
-fn main_cnf(filepath: PathBuf) {
-    eprintln!("lecture de la grille {filepath:?}");
-    let content: String = grid_read::file_read(filepath);
-    let grid_size: usize = grid_read::size(&content)
-        .trim()
-        .parse()
-        .expect("Taille incorrecte dans le fichier");
+/// Lis un fichier, ou l'entrée standard pour `-`, ou quitte le programme avec un message d'erreur
+fn read_input(path: &Path) -> String {
+    let mut content = String::new();
+    let result = if path == Path::new("-") {
+        io::stdin().read_to_string(&mut content)
+    } else {
+        File::open(path).and_then(|mut file| file.read_to_string(&mut content))
+    };
 
-    let mut grid: Grid = Grid::new(grid_size);
-    grid_read::fill_grid_from_file(&mut grid, &content);
+    if let Err(err) = result {
+        eprintln!("impossible de lire {path:?}: {err}");
+        std::process::exit(1);
+    }
+    content
+}
 
-    let stdout = std::io::stdout();
-    let mut output = CNFFile::new(&grid, BufWriter::new(stdout.lock()));
-    rules::write_all(&mut output, &grid);
-    output.save().unwrap();
+/// Lis et analyse un fichier de grille (`.grd` ou JSON), ou quitte le programme avec un message
+/// d'erreur
+fn read_grid(filepath: &Path) -> Grid {
+    info!("lecture de la grille {filepath:?}");
+    let content = read_input(filepath);
+
+    let grid = if json::is_json(&content) {
+        json::parse_grid(&content).map_err(|err| err.to_string())
+    } else {
+        grid_read::parse_with_progress(&content, &mut *reporter()).map_err(|err| err.to_string())
+    };
+
+    match grid {
+        Ok(grid) => grid,
+        Err(err) => {
+            eprintln!("grille invalide: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Quitte le programme si l'écriture de la sortie a échoué
+fn check_written(result: io::Result<()>) {
+    if let Err(err) = result {
+        eprintln!("erreur d'écriture: {err}");
+        std::process::exit(1);
+    }
+}
+
+/// Quitte le programme avant de générer la formule si elle dépasse les limites de `encoding`
+fn check_size(grid: &Grid, encoding: &EncodingArgs) {
+    let options = encoding.options();
+    let estimate = estimate::estimate(grid.size(), &options);
+    detail!(
+        "estimation: {} clauses, {} Mio",
+        estimate.clauses,
+        estimate.memory_bytes >> 20
+    );
+
+    if let Err(err) = estimate.check(&encoding.limits(), &options) {
+        eprintln!("{err}");
+        eprintln!("limites réglables avec `--max-clauses`, `--max-memory` ou `--no-limit`");
+        std::process::exit(1);
+    }
+}
+
+/// Quitte le programme avec une erreur d'utilisation si `format` ne vaut que pour `batch` et
+/// `bench`
+fn check_format(format: Format) {
+    if format == Format::Csv {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::InvalidValue,
+                "le format csv n'est disponible que pour `batch` et `bench`",
+            )
+            .exit();
+    }
+}
+
+fn main_cnf(filepath: &Path, encoding: &EncodingArgs, output: &OutputArgs) {
+    let grid = read_grid(filepath);
+    check_size(&grid, encoding);
+    let options = &encoding.options();
+
+    let mut cnf = CNFFile::new(&grid, output.open());
+    rules::write_rules(&mut cnf, &grid, options, &mut *reporter());
+    detail!(
+        "{} variables (dont {} auxiliaires), {} clauses, {} littéraux",
+        cnf.variable_count(),
+        cnf.aux_count(),
+        cnf.len(),
+        cnf.literal_count(),
+    );
+    check_written(cnf.save().and_then(|mut w| w.flush()));
+}
+
+/// Lis la taille de la grille dans le commentaire d'une formule DIMACS écrite par `cnf`, ou quitte
+/// le programme avec un message d'erreur
+fn read_formula_size(path: &Path) -> usize {
+    match File::open(path).map(|file| sat::formula_size(BufReader::new(file))) {
+        Ok(Some(size)) => size,
+        Ok(None) => {
+            eprintln!("{path:?}: taille de la grille absente (commentaire `c takuzu size`)");
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("impossible de lire {path:?}: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Lis le modèle produit par un SAT-solveur; `size` est la taille de la grille, déduite du
+/// modèle si elle n'est pas donnée
+fn main_sol(filepath: &Path, size: Option<usize>, format: Format, output: &OutputArgs) {
+    info!("lecture du fichier de résultats: {filepath:?}");
+    let content = read_input(filepath);
+    if size.is_none() {
+        info!(
+            "taille déduite du modèle, fausse avec des variables auxiliaires (donner la taille \
+            ou `--formula`)"
+        );
+    }
+    let result = sat::read_sat_file(content.as_bytes(), size, &mut *reporter());
+    let mut out = output.open();
+
+    if format == Format::Json {
+        let report = match result {
+            Ok(grid) => SolveReport {
+                size: Some(grid.size()),
+                givens: None,
+                solution: Some(grid),
+                status: Status::Solved,
+                timings: None,
+                error: None,
+            },
+            Err(err) => SolveReport {
+                size: None,
+                givens: None,
+                solution: None,
+                status: match err {
+                    sat::Error::Unsatisfiable => Status::Unsat,
+                    _ => Status::Error,
+                },
+                timings: None,
+                error: Some(format!("{err:?}")),
+            },
+        };
+        check_written(report.write(out));
+        return;
+    }
+
+    match result {
+        Ok(grid) => {
+            info!("grille: ");
+            check_written(grid.write(&mut out));
+        }
+        Err(err) => {
+            eprintln!("résultat illisible: {err:?}");
+            std::process::exit(1);
+        }
+    }
 }
 
-fn main_sol(filepath: PathBuf) {
-    eprintln!("lecture du fichier de résultats: {filepath:?}");
-    let file = std::io::BufReader::new(File::open(filepath).unwrap());
-    let grid = sat::read_sat_file(file).unwrap();
-    eprintln!("grille: ");
-    grid.print();
+/// Affiche la solution à côté de la grille dans un terminal; ailleurs, seule la solution est
+/// écrite, comme le fait `Grid::write`
+fn show_solution(
+    out: &mut dyn Write,
+    grid: &Grid,
+    solution: &Grid,
+    rules: RuleSet,
+    style: TermStyle,
+) -> io::Result<()> {
+    if style == TermStyle::PLAIN {
+        solution.write(out)
+    } else {
+        write!(out, "{}", term::side_by_side(grid, solution, rules, style))
+    }
 }
 
-/// Résoud immédiatement la grille avec `varisat`, un SAT-solveur intégré
-fn main_varisat(filepath: PathBuf) {
-    eprintln!("lecture de la grille {filepath:?}");
-    let content: String = grid_read::file_read(filepath);
-    let grid_size: usize = grid_read::size(&content)
-        .trim()
-        .parse()
-        .expect("Taille incorrecte dans le fichier");
+/// Résoud immédiatement la grille avec le solveur `backend` et vérifie l'unicité de la solution
+fn main_solve(
+    filepath: &Path,
+    encoding: &EncodingArgs,
+    backend: Backend,
+    format: Format,
+    output: &OutputArgs,
+) {
+    let grid = read_grid(filepath);
+    check_size(&grid, encoding);
+    let options = &encoding.options();
 
-    let mut grid: Grid = Grid::new(grid_size);
-    grid_read::fill_grid_from_file(&mut grid, &content);
+    info!("solveur: {}", backend.name());
+    let result =
+        solve::solve_with_backend(&grid, options, &mut *backend.create(), &mut *reporter())
+            .map_err(|err| err.to_string());
+    show_outcome(grid, result, options.rules, format, output);
+}
 
-    let mut output = CNFFile::new_varisat(&grid);
-    rules::write_all(&mut output, &grid);
-    let output = output.into_varisat();
+/// Résoud la grille sans SAT-solveur, avec le solveur natif
+fn main_native(filepath: &Path, rules: RuleSet, format: Format, output: &OutputArgs) {
+    let grid = read_grid(filepath);
 
-    let mut solver = varisat::Solver::new();
-    solver.add_formula(&output);
+    info!("solveur: natif");
+    let (result, duration) = reporter().step("solving", |_| native::solve(&grid, rules));
+    let result = result
+        .map(|outcome| {
+            let timings = Timings {
+                encoding: Duration::ZERO,
+                solving: duration,
+            };
+            (outcome, timings)
+        })
+        .map_err(|err| err.to_string());
+    show_outcome(grid, result, rules, format, output);
+}
 
-    eprintln!("[varisat] solving");
-    let instant_solving = Instant::now();
-    let model = match solver.solve() {
-        Ok(true) => {
-            eprintln!("\\ DONE ({:?})", instant_solving.elapsed());
-            solver.model().unwrap()
+/// Écrit l'issue de la résolution de `grid`, comme le mode `solve`
+fn show_outcome(
+    grid: Grid,
+    result: Result<(Outcome, Timings), String>,
+    rules: RuleSet,
+    format: Format,
+    output: &OutputArgs,
+) {
+    let mut out = output.open();
+
+    if format == Format::Json {
+        let report = match result {
+            Ok((outcome, timings)) => SolveReport {
+                size: Some(grid.size()),
+                status: Status::from(&outcome),
+                solution: match outcome {
+                    Outcome::Solved(solution) | Outcome::Ambiguous(solution, _) => Some(solution),
+                    Outcome::Unsat => None,
+                },
+                givens: Some(grid),
+                timings: Some(timings.into()),
+                error: None,
+            },
+            Err(err) => SolveReport {
+                size: Some(grid.size()),
+                givens: Some(grid),
+                solution: None,
+                status: Status::Error,
+                timings: None,
+                error: Some(err),
+            },
+        };
+        check_written(report.write(out));
+        return;
+    }
+
+    let style = output.style();
+    let written = match result {
+        Ok((Outcome::Solved(solution), _)) => {
+            info!("grille: ");
+            show_solution(&mut out, &grid, &solution, rules, style)
+        }
+        Ok((Outcome::Ambiguous(solution, other), _)) => {
+            eprintln!("attention: solution non unique");
+            info!("grille: ");
+            show_solution(&mut out, &grid, &solution, rules, style).and_then(|()| {
+                info!("autre solution: ");
+                show_solution(&mut out, &grid, &other, rules, style)
+            })
         }
-        Ok(false) => {
+        Ok((Outcome::Unsat, _)) => {
             eprintln!("\\ ERROR: unsat");
-            return;
+            if style != TermStyle::PLAIN {
+                write!(out, "{}", term::render(&grid, None, rules, style))
+            } else {
+                Ok(())
+            }
         }
         Err(err) => {
             eprintln!("\\ ERROR: {err}");
-            return;
+            std::process::exit(1);
         }
     };
+    check_written(written);
+}
 
-    match Grid::try_from(
-        model
-            .into_iter()
-            .map(|lit| Cell::Filled(lit.is_positive()))
-            .collect::<Vec<_>>(),
-    ) {
-        Ok(grid) => {
-            eprintln!("grille: ");
-            grid.print();
+/// Mesure la formule de la grille, règle par règle; avec `dry_run`, seule l'estimation de sa
+/// taille est écrite, sans la générer
+fn main_stats(
+    filepath: &Path,
+    encoding: &EncodingArgs,
+    dry_run: bool,
+    format: Format,
+    output: &OutputArgs,
+) {
+    let grid = read_grid(filepath);
+
+    if dry_run {
+        let estimate = estimate::estimate(grid.size(), &encoding.options());
+        let out = output.open();
+        check_written(match format {
+            Format::Json => estimate.write_json(out),
+            _ => estimate.write_text(out),
+        });
+        return;
+    }
+
+    check_size(&grid, encoding);
+    let stats = stats::analyse(&grid, &encoding.options(), &mut *reporter());
+
+    let out = output.open();
+    check_written(match format {
+        Format::Json => stats.write_json(out),
+        _ => stats.write_text(out),
+    });
+}
+
+/// Réécrit la grille sous forme canonique
+fn main_fmt(filepath: &Path, output: &OutputArgs) {
+    let grid = read_grid(filepath);
+    check_written(write!(output.open(), "{}", grid_read::format(&grid)));
+}
+
+/// Résoud toutes les grilles d'une collection, ou de toutes les collections d'un dossier
+fn main_batch(
+    path: &Path,
+    encoding: &EncodingArgs,
+    backend: Backend,
+    format: Format,
+    output: &OutputArgs,
+) {
+    let files = match batch::collect_files(path) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("impossible de lister {path:?}: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let (options, limits) = (encoding.options(), encoding.limits());
+    let report = batch::run(&files, &options, &limits, &backend, |entry| {
+        info!(
+            "[batch] {}:{} {}",
+            entry.file,
+            entry.name,
+            entry.status.as_str()
+        );
+        detail!(
+            "        encodage {:.3} ms, résolution {:.3} ms",
+            entry.encoding_ms,
+            entry.solving_ms,
+        );
+    });
+
+    let out = output.open();
+    check_written(match format {
+        Format::Text => report.write_text(out),
+        Format::Csv => report.write_csv(out),
+        Format::Json => report.write_json(out),
+    });
+}
+
+/// Grilles mesurées par `bench`, ou vérifiées par `crosscheck` (voir [`PuzzleArgs`])
+enum PuzzleSource {
+    /// Grilles générées: tailles, nombre par taille, proportion de cellules données et graine
+    Generated(Vec<usize>, usize, f64, u64),
+    /// Grilles d'une collection ou d'un dossier
+    Files(PathBuf),
+}
+
+/// Génère ou lit les grilles, ou quitte le programme avec un message d'erreur
+fn load_puzzles(puzzles: PuzzleSource) -> Vec<(String, Grid)> {
+    let puzzles = match puzzles {
+        PuzzleSource::Generated(sizes, count, density, seed) => {
+            info!("génération de {} grilles", sizes.len() * count);
+            bench::generate(&sizes, count, density, seed).map_err(|err| err.to_string())
+        }
+        PuzzleSource::Files(path) => batch::collect_files(&path)
+            .map_err(|err| format!("impossible de lister {path:?}: {err}"))
+            .and_then(|files| batch::load(&files)),
+    };
+    match puzzles {
+        Ok(puzzles) => puzzles,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Mesure chaque grille avec chaque combinaison d'encodages et chaque solveur
+fn main_bench(
+    puzzles: PuzzleSource,
+    rules: RuleSet,
+    limits: &LimitArgs,
+    backends: &[Backend],
+    format: Format,
+    output: &OutputArgs,
+) {
+    let puzzles = load_puzzles(puzzles);
+    let report = bench::run(&puzzles, rules, backends, &limits.limits(), |run| {
+        info!(
+            "[bench] {} {}/{} {}: {}",
+            run.puzzle, run.balance, run.uniqueness, run.backend, run.status
+        );
+        detail!(
+            "        encodage {:.3} ms, résolution {:.3} ms{}",
+            run.encoding_ms,
+            run.solving_ms,
+            match run.peak_memory_bytes {
+                Some(bytes) => format!(", {} Kio", bytes >> 10),
+                None => String::new(),
+            },
+        );
+    });
+
+    let out = output.open();
+    check_written(match format {
+        Format::Text => report.write_text(out),
+        Format::Csv => report.write_csv(out),
+        Format::Json => report.write_json(out),
+    });
+}
+
+/// Résoud chaque grille par la formule `varisat`, le fichier DIMACS et le solveur natif, et quitte
+/// le programme en erreur si les issues ne s'accordent pas toutes
+fn main_crosscheck(
+    puzzles: PuzzleSource,
+    encoding: &EncodingArgs,
+    dimacs: &DimacsSolver,
+    output: &OutputArgs,
+) {
+    let puzzles = load_puzzles(puzzles);
+    let options = encoding.options();
+    let limits = encoding.limits();
+    let mut out = output.open();
+    let (mut skipped, mut disagreements) = (0, 0);
+
+    for (name, grid) in &puzzles {
+        let checked = if grid.size() > bitboard::MAX_SIZE {
+            Err(native::Error::TooLarge(grid.size()).to_string())
+        } else {
+            estimate::estimate(grid.size(), &options)
+                .check(&limits, &options)
+                .map_err(|err| err.to_string())
+        };
+        if let Err(err) = checked {
+            info!("[crosscheck] {name}: ignorée, {err}");
+            skipped += 1;
+            continue;
         }
-        Err(()) => unreachable!("invalid model"),
+
+        let report = crosscheck::crosscheck(grid, &options, dimacs);
+        let line = match report.status() {
+            Some(status) => format!("{name}: accord, {}", status.as_str()),
+            None => {
+                disagreements += 1;
+                let statuses: Vec<_> = report
+                    .checks
+                    .iter()
+                    .map(|check| match &check.outcome {
+                        Ok(outcome) => {
+                            format!("{} {}", check.method.name(), Status::from(outcome).as_str())
+                        }
+                        Err(_) => format!("{} {}", check.method.name(), Status::Error.as_str()),
+                    })
+                    .collect();
+                format!("{name}: DÉSACCORD ({})", statuses.join(", "))
+            }
+        };
+
+        let written = writeln!(out, "{line}").and_then(|()| {
+            report
+                .problems
+                .iter()
+                .try_for_each(|problem| writeln!(out, "    {problem}"))
+        });
+        check_written(written);
+    }
+
+    check_written(
+        writeln!(
+            out,
+            "{} grilles vérifiées, {disagreements} désaccords, {skipped} ignorées",
+            puzzles.len() - skipped
+        )
+        .and_then(|()| out.flush()),
+    );
+    if disagreements > 0 {
+        std::process::exit(1);
     }
 }
 
-/// exe: nom de l'exécutable pour le message d'aide
-fn help(exe: &str) {
-    eprintln!(
-        "Usage: `{0} sol <fichier.takuzu>`\n    ou `{0} cnf <fichier.resultat>\n    ou `{0} varisat <fichier.takuzu>`",
-        exe,
+/// Résoud la grille pour l'afficher: renvoie une solution si elle existe, en prévenant si elle
+/// n'est pas unique. Quitte le programme si la formule dépasse les limites de `encoding`.
+fn solution_for_display(grid: &Grid, encoding: &EncodingArgs) -> Option<Grid> {
+    check_size(grid, encoding);
+    let result = solve::solve_with_backend(
+        grid,
+        &encoding.options(),
+        &mut VarisatBackend::default(),
+        &mut Silent,
     );
+
+    match result {
+        Ok((Outcome::Solved(solution), _)) => Some(solution),
+        Ok((Outcome::Ambiguous(solution, _), _)) => {
+            eprintln!("attention: solution non unique");
+            Some(solution)
+        }
+        Ok((Outcome::Unsat, _)) => {
+            eprintln!("attention: grille insatisfiable, solution omise");
+            None
+        }
+        Err(err) => {
+            eprintln!("attention: {err}, solution omise");
+            None
+        }
+    }
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// Dessine la grille en SVG, avec sa solution si `with_solution`
+fn main_svg(
+    filepath: &Path,
+    with_solution: bool,
+    options: &svg::SvgOptions,
+    encoding: &EncodingArgs,
+    output: &OutputArgs,
+) {
+    let grid = read_grid(filepath);
+
+    let solution = if with_solution {
+        solution_for_display(&grid, encoding)
+    } else {
+        None
+    };
+
+    check_written(write!(
+        output.open(),
+        "{}",
+        svg::render(&grid, solution.as_ref(), options),
+    ));
+}
+
+/// Partie interactive dans le terminal, sauvegardée à côté de la grille (extension `.sav`). Les
+/// indices et vérifications qui font appel au solveur utilisent les encodages de `encoding`.
+fn main_play(filepath: &Path, encoding: &EncodingArgs) {
+    // La partie lit le clavier, et se sauvegarde à côté du fichier de la grille
+    if filepath == Path::new("-") {
+        eprintln!("play ne lit pas la grille sur l'entrée standard: donnez un fichier");
+        std::process::exit(1);
+    }
+    let save_path = filepath.with_extension("sav");
+    let grid = read_grid(filepath);
+    check_size(&grid, encoding);
+
+    if let Err(err) = play::run(grid, encoding.options(), &save_path) {
+        eprintln!("erreur du terminal: {err}");
+        std::process::exit(1);
+    }
+}
+
+/// Session interactive sur la grille; les commandes sont lues dans `script` s'il est donné, et
+/// recopiées dans la sortie
+fn main_repl(filepath: &Path, script: Option<&Path>, encoding: &EncodingArgs, backend: Backend) {
+    // Sans script, les commandes sont lues sur l'entrée standard
+    if filepath == Path::new("-") && script.is_none() {
+        eprintln!(
+            "repl ne lit la grille sur l'entrée standard qu'avec un fichier de commandes: \
+            donnez un fichier de grille, ou un script"
+        );
+        std::process::exit(1);
+    }
+    let grid = read_grid(filepath);
+    check_size(&grid, encoding);
+    let mut session = repl::Session::new(grid, &encoding.options(), TermStyle::detect(), &backend);
+
+    let (input, echo): (Box<dyn BufRead>, bool) = match script {
+        Some(script) => match File::open(script) {
+            Ok(file) => (Box::new(BufReader::new(file)), true),
+            Err(err) => {
+                eprintln!("impossible d'ouvrir {script:?}: {err}");
+                std::process::exit(1);
+            }
+        },
+        None => (Box::new(io::stdin().lock()), false),
+    };
+
+    if let Err(err) = repl::run(&mut session, input, echo) {
+        eprintln!("erreur d'entrée/sortie: {err}");
+        std::process::exit(1);
+    }
+}
+
+/// Exporte la grille en LaTeX: image TikZ autonome, ou tableau des statistiques de la formule
+fn main_tikz(filepath: &Path, content: TikzContent, encoding: &EncodingArgs, output: &OutputArgs) {
+    let grid = read_grid(filepath);
+
+    let layout = match content {
+        TikzContent::Puzzle => tikz::Layout::Puzzle,
+        TikzContent::Solution => tikz::Layout::Solution,
+        TikzContent::Both => tikz::Layout::SideBySide,
+        TikzContent::Stats => {
+            check_size(&grid, encoding);
+            let mut cnf = CNFFile::new_varisat(&grid);
+            let stats = rules::write_rules(&mut cnf, &grid, &encoding.options(), &mut Silent);
+            let table = tikz::stats_table(&stats, grid.to_literals().len());
+            check_written(writeln!(output.open(), "{table}"));
+            return;
+        }
+    };
+
+    let solution = match layout {
+        tikz::Layout::Puzzle => None,
+        _ => solution_for_display(&grid, encoding),
+    };
+
+    check_written(write!(
+        output.open(),
+        "{}",
+        tikz::standalone(&tikz::picture(&grid, solution.as_ref(), layout)),
+    ));
+}
+
+/// Importe une grille depuis un identifiant externe et l'écrit sous forme canonique
+fn main_import(id: &str, output: &OutputArgs) {
+    // Les URL pzprjs sont les seules à contenir des `/`
+    let puzzle = if id.contains('/') {
+        pzprjs::parse(id)
+            .map(|grid| (grid, RuleSet::TAKUZU))
+            .map_err(|err| err.to_string())
+    } else {
+        tatham::parse(id).map_err(|err| err.to_string())
+    };
 
-    match args.as_slice() {
-        [_, mode, filename] if mode == "sol" => main_sol(filename.into()),
-        [_, mode, filename] if mode == "cnf" => main_cnf(filename.into()),
-        [_, mode, filename] if mode == "varisat" => main_varisat(filename.into()),
-        [exe, _, _] => {
-            eprintln!("Mode inconnu.");
-            help(exe);
+    let (grid, rules) = match puzzle {
+        Ok(puzzle) => puzzle,
+        Err(err) => {
+            eprintln!("identifiant invalide: {err}");
+            std::process::exit(1);
         }
-        [exe] => {
-            eprintln!("Deux arguments attendus.");
-            help(exe);
+    };
+
+    // Le format `.grd` ne garde pas les règles: elles sont à redonner pour résoudre la grille
+    if rules != RuleSet::TAKUZU {
+        eprintln!(
+            "attention: grille prévue pour les règles `{0}`, à résoudre avec `--rules {0}`",
+            rules.name()
+        );
+    }
+    check_written(write!(output.open(), "{}", grid_read::format(&grid)));
+}
+
+/// Exporte une grille vers un identifiant externe
+fn main_export(target: ExportTarget, filepath: &Path, rules: RuleSet, output: &OutputArgs) {
+    let grid = read_grid(filepath);
+
+    let id = match target {
+        ExportTarget::Tatham => tatham::export(&grid, rules).map_err(|err| err.to_string()),
+        ExportTarget::Pzprjs if rules != RuleSet::TAKUZU => {
+            Err("pzprjs ne connaît que les règles du Takuzu".to_string())
         }
-        _ => {
-            eprintln!("Deux arguments attendus.");
+        ExportTarget::Pzprjs => Ok(pzprjs::export(&grid)),
+    };
+
+    match id {
+        Ok(id) => check_written(writeln!(output.open(), "{id}")),
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn main() {
+    let cli = Cli::parse();
+
+    let verbosity = match (cli.quiet, cli.verbose) {
+        (true, _) => 0,
+        (_, true) => 2,
+        _ => 1,
+    };
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+    let _ = PROGRESS.set(cli.progress);
+
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("le pool de threads n'est créé qu'une fois");
+    }
 
-    #[test]
-    fn grid_get_set() {
-        let mut g = Grid::new(6);
-        g.set(0, 1, true);
-        g.set(0, 0, false);
-        assert_eq!(g.get(0, 1), Cell::Filled(true));
-        assert_eq!(g.get(0, 0), Cell::Filled(false));
-        assert_eq!(g.get(0, 0), Cell::Filled(false));
+    match cli.mode {
+        Mode::Cnf {
+            grid,
+            encoding,
+            output,
+        } => main_cnf(&grid, &encoding, &output),
+        Mode::Sol {
+            result,
+            size,
+            formula,
+            format,
+            output,
+        } => {
+            check_format(format);
+            let size = size.or_else(|| formula.as_deref().map(read_formula_size));
+            main_sol(&result, size, format, &output)
+        }
+        Mode::Solve {
+            grid,
+            encoding,
+            solver,
+            format,
+            output,
+        } => {
+            check_format(format);
+            main_solve(&grid, &encoding, solver.into(), format, &output)
+        }
+        Mode::Native {
+            grid,
+            rules,
+            format,
+            output,
+        } => {
+            check_format(format);
+            main_native(&grid, rules, format, &output)
+        }
+        Mode::Stats {
+            grid,
+            encoding,
+            estimate,
+            format,
+            output,
+        } => {
+            check_format(format);
+            main_stats(&grid, &encoding, estimate, format, &output)
+        }
+        Mode::Fmt { grid, output } => main_fmt(&grid, &output),
+        Mode::Batch {
+            path,
+            encoding,
+            solver,
+            format,
+            output,
+        } => main_batch(&path, &encoding, solver.into(), format, &output),
+        Mode::Bench {
+            puzzles,
+            rules,
+            limits,
+            mut backends,
+            solvers,
+            timeout,
+            format,
+            output,
+        } => {
+            let puzzles = puzzles.source(&[4, 6, 8, 10, 12, 14, 16, 20, 24, 30], 3);
+            if backends.is_empty() && solvers.is_empty() {
+                backends = Backend::builtin();
+            }
+            backends.extend(
+                solvers
+                    .into_iter()
+                    .map(|command| Backend::External { command, timeout }),
+            );
+            main_bench(puzzles, rules, &limits, &backends, format, &output)
+        }
+        Mode::Crosscheck {
+            puzzles,
+            encoding,
+            solver,
+            timeout,
+            output,
+        } => {
+            let puzzles = puzzles.source(&[4, 6, 8], 10);
+            let dimacs = match solver {
+                Some(command) => DimacsSolver::External { command, timeout },
+                None => DimacsSolver::Varisat,
+            };
+            main_crosscheck(puzzles, &encoding, &dimacs, &output)
+        }
+        Mode::Play { grid, encoding } => main_play(&grid, &encoding),
+        Mode::Repl {
+            grid,
+            script,
+            encoding,
+            solver,
+        } => main_repl(&grid, script.as_deref(), &encoding, solver.into()),
+        Mode::Svg {
+            grid,
+            solution,
+            cell_size,
+            coordinates,
+            show_rules,
+            encoding,
+            output,
+        } => {
+            let options = svg::SvgOptions {
+                cell_size,
+                coordinates,
+                rules: Some(encoding.rules).filter(|_| show_rules),
+            };
+            main_svg(&grid, solution, &options, &encoding, &output)
+        }
+        Mode::Tikz {
+            grid,
+            content,
+            encoding,
+            output,
+        } => main_tikz(&grid, content, &encoding, &output),
+        Mode::Import { id, output } => main_import(&id, &output),
+        Mode::Export {
+            target,
+            grid,
+            rules,
+            output,
+        } => main_export(target, &grid, rules, &output),
     }
 }
@@ -0,0 +1,299 @@
+//! Solveur dédié au Takuzu, sans SAT-solveur: propagation des trois règles sur un [`Bitboard`],
+//! puis retour sur trace.
+//!
+//! Il donne les mêmes issues que [`crate::solve`] (solution unique, aucune, ou deux solutions
+//! distinctes) et sert d'oracle pour en tester les encodages.
+use crate::bitboard::{self, Bitboard, Line, MAX_SIZE};
+use crate::rules::RuleSet;
+use crate::solve::Outcome;
+use crate::{Cell, Grid};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// La grille est plus grande que ce que représente un [`Bitboard`]
+    TooLarge(usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::TooLarge(size) => write!(
+                f,
+                "grille {size}x{size} trop grande pour le solveur natif (au plus \
+                {MAX_SIZE}x{MAX_SIZE})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Contradiction trouvée pendant la propagation: la branche n'a pas de solution
+struct Contradiction;
+
+/// Cellules vides qui compléteraient trois cellules identiques consécutives valant `value`
+fn completes_triple(line: Line, size: usize, value: bool) -> u64 {
+    let (same, empty) = (line.cells(value), line.empty(size));
+    empty & ((same >> 1 & same >> 2) | (same << 1 & same >> 1) | (same << 1 & same << 2))
+}
+
+/// Vérifie les règles sur la ligne `line`, et renvoie les valeurs imposées à ses cellules vides:
+/// `(cellules à 0, cellules à 1)`
+fn forced(line: Line, size: usize, rules: RuleSet) -> Result<(u64, u64), Contradiction> {
+    let mut forced = [0; 2];
+
+    for value in [false, true] {
+        if rules.no_triples {
+            if line.triples(value) != 0 {
+                return Err(Contradiction);
+            }
+            forced[!value as usize] |= completes_triple(line, size, value);
+        }
+        if rules.balance {
+            match line.count(value) {
+                count if count > size / 2 => return Err(Contradiction),
+                count if count == size / 2 => forced[!value as usize] |= line.empty(size),
+                _ => {}
+            }
+        }
+    }
+
+    if forced[0] & forced[1] != 0 {
+        return Err(Contradiction);
+    }
+    Ok((forced[0], forced[1]))
+}
+
+/// Indique si deux lignes complètes sont identiques, parmi les rangées ou parmi les colonnes
+fn has_duplicate(board: &Bitboard) -> bool {
+    let size = board.size();
+    [false, true].iter().any(|&column| {
+        let lines = board.lines(column);
+        (0..size).any(|a| {
+            lines[a].is_complete(size) && lines[a + 1..].iter().any(|&other| other == lines[a])
+        })
+    })
+}
+
+/// Impose aux cellules vides les valeurs qui découlent des règles, jusqu'à ce que plus rien ne
+/// change. Sans contradiction, la grille obtenue respecte les règles.
+fn propagate(board: &mut Bitboard, rules: RuleSet) -> Result<(), Contradiction> {
+    let size = board.size();
+
+    loop {
+        let mut changed = false;
+
+        for column in [false, true] {
+            for k in 0..size {
+                let line = board.lines(column)[k];
+                let (zeros, ones) = forced(line, size, rules)?;
+                let (mut zeros, mut ones) = (zeros, ones);
+
+                // Règle 3: s'il ne reste que deux cellules vides, une à 0 et une à 1, la
+                // complétion qui reproduirait une ligne complète est interdite
+                let empty = line.empty(size);
+                if rules.unique_lines
+                    && rules.balance
+                    && empty.count_ones() == 2
+                    && line.count(true) == size / 2 - 1
+                    && zeros | ones == 0
+                {
+                    let low = empty & empty.wrapping_neg();
+                    let lines = board.lines(column);
+                    let taken = |ones: u64| {
+                        lines
+                            .iter()
+                            .any(|other| other.is_complete(size) && other.ones == line.ones | ones)
+                    };
+                    match (taken(low), taken(empty & !low)) {
+                        (true, true) => return Err(Contradiction),
+                        (true, false) => (zeros, ones) = (low, empty & !low),
+                        (false, true) => (zeros, ones) = (empty & !low, low),
+                        (false, false) => {}
+                    }
+                }
+
+                for (mask, value) in [(zeros, false), (ones, true)] {
+                    for i in bitboard::bits(mask) {
+                        let (x, y) = bitboard::position(k, i, column);
+                        board.set(x, y, Cell::Filled(value));
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if !changed {
+            if rules.unique_lines && has_duplicate(board) {
+                return Err(Contradiction);
+            }
+            return Ok(());
+        }
+    }
+}
+
+/// Cellule vide sur laquelle brancher: dans la ligne qui a le moins de cellules vides, et la
+/// valeur à essayer d'abord, la moins présente dans cette ligne
+fn branch(board: &Bitboard) -> Option<(usize, usize, bool)> {
+    let size = board.size();
+
+    [false, true]
+        .iter()
+        .flat_map(|&column| {
+            board
+                .lines(column)
+                .iter()
+                .enumerate()
+                .map(move |(k, &line)| (column, k, line))
+        })
+        .filter(|(_, _, line)| !line.is_complete(size))
+        .min_by_key(|(_, _, line)| line.empty(size).count_ones())
+        .map(|(column, k, line)| {
+            let i = line.empty(size).trailing_zeros() as usize;
+            let (x, y) = bitboard::position(k, i, column);
+            (x, y, line.count(true) < line.count(false))
+        })
+}
+
+/// Cherche au plus `2 - solutions.len()` solutions à partir de `board`, par retour sur trace
+fn search(mut board: Bitboard, rules: RuleSet, solutions: &mut Vec<Grid>) {
+    if propagate(&mut board, rules).is_err() {
+        return;
+    }
+
+    match branch(&board) {
+        None => solutions.push(board.to_grid()),
+        Some((x, y, value)) => {
+            for value in [value, !value] {
+                let mut next = board.clone();
+                next.set(x, y, Cell::Filled(value));
+                search(next, rules, solutions);
+                if solutions.len() == 2 {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Résoud la grille selon `rules` et vérifie l'unicité de la solution
+pub fn solve(grid: &Grid, rules: RuleSet) -> Result<Outcome, Error> {
+    let board = Bitboard::new(grid).ok_or(Error::TooLarge(grid.size()))?;
+
+    let mut solutions = Vec::new();
+    search(board, rules, &mut solutions);
+
+    let mut solutions = solutions.into_iter();
+    Ok(match (solutions.next(), solutions.next()) {
+        (None, _) => Outcome::Unsat,
+        (Some(solution), None) => Outcome::Solved(solution),
+        (Some(first), Some(second)) => Outcome::Ambiguous(first, second),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::VarisatBackend;
+    use crate::generate::{self, XorShift};
+    use crate::grid_read;
+    use crate::progress::Silent;
+    use crate::rules::{BalanceEncoding, EncodingOptions, UniquenessEncoding};
+    use crate::solve::{self, Status};
+    use crate::validate;
+
+    #[test]
+    fn outcomes() {
+        let grid = grid_read::parse("4\n.1.0\n..0.\n.0..\n11.0\n").unwrap();
+        match solve(&grid, RuleSet::TAKUZU).unwrap() {
+            Outcome::Solved(solution) => {
+                assert_eq!(grid_read::format(&solution), "4\n0110\n1001\n0011\n1100\n")
+            }
+            outcome => panic!("{:?}", outcome),
+        }
+
+        match solve(&Grid::new(4), RuleSet::TAKUZU).unwrap() {
+            Outcome::Ambiguous(first, second) => {
+                assert_ne!(first.inner(), second.inner());
+                assert!(validate::is_solution(&first, RuleSet::TAKUZU));
+                assert!(validate::is_solution(&second, RuleSet::TAKUZU));
+            }
+            outcome => panic!("{:?}", outcome),
+        }
+
+        let grid = grid_read::parse("4\n000.\n....\n....\n....\n").unwrap();
+        assert!(matches!(
+            solve(&grid, RuleSet::TAKUZU).unwrap(),
+            Outcome::Unsat
+        ));
+
+        // La seule complétion répète la deuxième ligne
+        let grid = grid_read::parse("4\n0101\n1010\n0101\n....\n").unwrap();
+        assert!(matches!(
+            solve(&grid, RuleSet::TAKUZU).unwrap(),
+            Outcome::Unsat
+        ));
+        assert!(matches!(
+            solve(&grid, RuleSet::UNRULY).unwrap(),
+            Outcome::Solved(_)
+        ));
+
+        assert_eq!(
+            solve(&Grid::new(66), RuleSet::TAKUZU).unwrap_err(),
+            Error::TooLarge(66)
+        );
+    }
+
+    /// Encodages compacts: la règle 3 développée serait trop grande en 10x10
+    fn options(rules: RuleSet) -> EncodingOptions {
+        EncodingOptions {
+            rules,
+            balance: BalanceEncoding::Sequential,
+            uniqueness: UniquenessEncoding::Tseitin,
+        }
+    }
+
+    /// Mêmes issues que le SAT-solveur, sur des grilles aléatoires plus ou moins remplies
+    #[test]
+    fn matches_sat() {
+        let mut rng = XorShift::new(3);
+        for size in [4, 6, 8, 10] {
+            for density in [0.2, 0.4, 0.6] {
+                for _ in 0..5 {
+                    let mut grid = generate::random_puzzle(size, density, &mut rng).unwrap();
+                    // Une cellule modifiée au hasard rend parfois la grille insatisfiable
+                    if rng.bool() {
+                        let (x, y) = (rng.below(size), rng.below(size));
+                        grid.set(x, y, rng.bool());
+                    }
+
+                    for rules in [RuleSet::TAKUZU, RuleSet::UNRULY] {
+                        let native = solve(&grid, rules).unwrap();
+                        let (sat, _) = solve::solve_with_backend(
+                            &grid,
+                            &options(rules),
+                            &mut VarisatBackend::default(),
+                            &mut Silent,
+                        )
+                        .unwrap();
+                        let context = grid_read::format(&grid);
+
+                        assert_eq!(Status::from(&native), Status::from(&sat), "{}", context);
+                        match (native, sat) {
+                            (Outcome::Solved(native), Outcome::Solved(sat)) => {
+                                assert_eq!(native.inner(), sat.inner(), "{}", context)
+                            }
+                            (Outcome::Ambiguous(first, second), _) => {
+                                assert_ne!(first.inner(), second.inner());
+                                assert!(validate::is_solution(&first, rules), "{}", context);
+                                assert!(validate::is_solution(&second, rules), "{}", context);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
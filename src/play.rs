@@ -0,0 +1,609 @@
+//! Mode de jeu interactif dans le terminal.
+//!
+//! L'état de la partie ([`Game`]) est indépendant du terminal: les touches sont traduites en
+//! [`Action`], ce qui permet de tester la logique sans interface. La progression est sauvegardée
+//! sous forme de [collection](crate::collection) à deux grilles, `puzzle` et `progress`.
+
+use crate::term::{self, TermStyle};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, terminal};
+use inf402_takuzu::backend::{self, SatBackend, VarisatBackend};
+use inf402_takuzu::collection;
+use inf402_takuzu::grid_read;
+use inf402_takuzu::human;
+use inf402_takuzu::progress::Silent;
+use inf402_takuzu::rules::{self, EncodingOptions};
+use inf402_takuzu::solve::{self, Outcome, Timings};
+use inf402_takuzu::validate;
+use inf402_takuzu::{Cell, Grid};
+use std::fmt::{Display, Formatter};
+use std::io::{self, Write};
+use std::path::Path;
+use varisat::Lit;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Le fichier de sauvegarde n'est pas une collection valide
+    Collection(collection::Error),
+
+    /// L'une des grilles de la sauvegarde est invalide
+    Grid(grid_read::Error),
+
+    /// La sauvegarde ne contient pas la grille de ce nom
+    MissingGrid(&'static str),
+
+    /// La progression n'a pas la taille de la grille, ou modifie une cellule donnée
+    Mismatch,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Collection(err) => write!(f, "sauvegarde invalide: {err}"),
+            Error::Grid(err) => write!(f, "grille invalide dans la sauvegarde: {err}"),
+            Error::MissingGrid(name) => write!(f, "grille `{name}` absente de la sauvegarde"),
+            Error::Mismatch => write!(f, "la progression ne correspond pas à la grille"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Ce que le joueur peut faire
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+    Move(Direction),
+
+    /// Fait passer la cellule de vide à 0, de 0 à 1 et de 1 à vide
+    Toggle,
+
+    /// Place une valeur, ou vide la cellule avec `None`
+    Set(Option<bool>),
+
+    Undo,
+    Redo,
+
+    /// Remplit une cellule, en expliquant la déduction quand c'est possible
+    Hint,
+
+    /// Indique si la grille peut encore être résolue à partir de l'état actuel
+    Check,
+}
+
+/// Modification d'une cellule, pour annuler et refaire
+#[derive(Clone, Copy, Debug)]
+struct Change {
+    x: usize,
+    y: usize,
+    before: Cell,
+    after: Cell,
+}
+
+/// État d'une partie
+#[derive(Debug)]
+pub struct Game {
+    /// Grille de départ, dont les cellules ne peuvent pas être modifiées
+    pub puzzle: Grid,
+
+    /// Grille en cours, qui contient les cellules données
+    pub grid: Grid,
+
+    /// Règles du jeu, et encodages utilisés quand il faut faire appel au solveur
+    pub options: EncodingOptions,
+    pub cursor: (usize, usize),
+
+    /// Message affiché sous la grille après la dernière action
+    pub message: String,
+
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+}
+
+impl Game {
+    pub fn new(puzzle: Grid, options: EncodingOptions) -> Self {
+        Game {
+            grid: puzzle.clone(),
+            puzzle,
+            options,
+            cursor: (0, 0),
+            message: String::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Grille complète et sans infraction aux règles
+    pub fn is_solved(&self) -> bool {
+        validate::is_solution(&self.grid, self.options.rules)
+    }
+
+    fn put(&mut self, x: usize, y: usize, cell: Cell) {
+        self.grid.set_cell(x, y, cell);
+    }
+
+    /// Modifie une cellule non donnée en gardant la trace du changement
+    fn write(&mut self, x: usize, y: usize, after: Cell) {
+        if self.puzzle.get(x, y) != Cell::Empty {
+            self.message = "cellule donnée, non modifiable".into();
+            return;
+        }
+
+        let before = self.grid.get(x, y);
+        if before == after {
+            return;
+        }
+        self.put(x, y, after);
+        self.undo.push(Change {
+            x,
+            y,
+            before,
+            after,
+        });
+        self.redo.clear();
+
+        if self.is_solved() {
+            self.message = "Bravo, la grille est résolue !".into();
+        }
+    }
+
+    pub fn apply(&mut self, action: Action) {
+        self.message.clear();
+        let (x, y) = self.cursor;
+        let last = self.grid.size() - 1;
+
+        match action {
+            Action::Move(Direction::Up) => self.cursor.1 = y.saturating_sub(1),
+            Action::Move(Direction::Down) => self.cursor.1 = (y + 1).min(last),
+            Action::Move(Direction::Left) => self.cursor.0 = x.saturating_sub(1),
+            Action::Move(Direction::Right) => self.cursor.0 = (x + 1).min(last),
+            Action::Toggle => {
+                let next = match self.grid.get(x, y) {
+                    Cell::Empty => Cell::Filled(false),
+                    Cell::Filled(false) => Cell::Filled(true),
+                    Cell::Filled(true) => Cell::Empty,
+                };
+                self.write(x, y, next);
+            }
+            Action::Set(value) => self.write(x, y, value.map_or(Cell::Empty, Cell::Filled)),
+            Action::Undo => match self.undo.pop() {
+                Some(change) => {
+                    self.put(change.x, change.y, change.before);
+                    self.cursor = (change.x, change.y);
+                    self.redo.push(change);
+                }
+                None => self.message = "rien à annuler".into(),
+            },
+            Action::Redo => match self.redo.pop() {
+                Some(change) => {
+                    self.put(change.x, change.y, change.after);
+                    self.cursor = (change.x, change.y);
+                    self.undo.push(change);
+                }
+                None => self.message = "rien à refaire".into(),
+            },
+            Action::Hint => self.hint(),
+            Action::Check => self.check(),
+        }
+    }
+
+    /// Les erreurs visibles sont signalées en premier; sinon une déduction simple est appliquée,
+    /// et à défaut le solveur SAT donne la valeur d'une cellule qu'il prouve
+    fn hint(&mut self) {
+        if self.is_solved() {
+            self.message = "la grille est déjà résolue".into();
+            return;
+        }
+
+        if let Some(&cell) = validate::conflicts(&self.grid, self.options.rules)
+            .iter()
+            .next()
+        {
+            self.cursor = cell;
+            self.message = "la grille enfreint déjà les règles (cellules en rouge)".into();
+            return;
+        }
+
+        if let Some(deduction) = human::next_deduction(&self.grid, self.options.rules) {
+            self.cursor = (deduction.x, deduction.y);
+            self.write(deduction.x, deduction.y, Cell::Filled(deduction.value));
+            self.message = format!("indice: {deduction}");
+            return;
+        }
+
+        match self.solver_hint() {
+            Ok((x, y, value)) => {
+                self.cursor = (x, y);
+                self.write(x, y, Cell::Filled(value));
+                self.message = format!("indice (solveur): ({x}, {y}) vaut {}", value as u8);
+            }
+            Err(message) => self.message = message,
+        }
+    }
+
+    /// Cellule vide dont le solveur SAT prouve la valeur, ou le message à afficher à défaut
+    fn solver_hint(&self) -> Result<(usize, usize, bool), String> {
+        let forced = match self.solve() {
+            // Solution unique: toutes les cellules vides sont imposées
+            Ok((Outcome::Solved(solution), _)) => self.forced_cell(&solution, &solution),
+            Ok((Outcome::Ambiguous(first, second), _)) => self.forced_cell(&first, &second),
+            Ok((Outcome::Unsat, _)) => {
+                return Err("impasse: au moins une valeur placée est fausse".into())
+            }
+            Err(err) => Err(err),
+        };
+
+        match forced {
+            Ok(Some(cell)) => Ok(cell),
+            Ok(None) => {
+                Err("plusieurs solutions: aucune cellule vide n'a de valeur imposée".into())
+            }
+            Err(err) => Err(format!("erreur du solveur: {err}")),
+        }
+    }
+
+    /// Première cellule vide dont le solveur prouve la valeur: elle vaut la même chose dans les
+    /// deux solutions `first` et `second`, et la valeur opposée rend la grille insatisfiable
+    fn forced_cell(
+        &self,
+        first: &Grid,
+        second: &Grid,
+    ) -> Result<Option<(usize, usize, bool)>, backend::Error> {
+        let size = self.grid.size();
+        let (formula, _) = rules::encode(&self.grid, &self.options, &mut Silent);
+        let mut solver = VarisatBackend::default();
+        solver.add_formula(&formula);
+
+        for y in 0..size {
+            for x in 0..size {
+                let value = match (self.grid.get(x, y), first.get(x, y), second.get(x, y)) {
+                    (Cell::Empty, Cell::Filled(a), Cell::Filled(b)) if a == b => a,
+                    _ => continue,
+                };
+                solver.assume(&[Lit::from_index(y * size + x, !value)]);
+                if !solver.solve()? {
+                    return Ok(Some((x, y, value)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Résoud la grille en cours avec les encodages de la partie
+    fn solve(&self) -> Result<(Outcome, Timings), backend::Error> {
+        solve::solve_with_backend(
+            &self.grid,
+            &self.options,
+            &mut VarisatBackend::default(),
+            &mut Silent,
+        )
+    }
+
+    fn check(&mut self) {
+        if self.is_solved() {
+            self.message = "Bravo, la grille est résolue !".into();
+            return;
+        }
+
+        self.message = match self.solve() {
+            Ok((Outcome::Solved(_), _)) | Ok((Outcome::Ambiguous(_, _), _)) => {
+                "pas d'erreur pour l'instant".into()
+            }
+            Ok((Outcome::Unsat, _)) => "impasse: au moins une valeur placée est fausse".into(),
+            Err(err) => format!("erreur du solveur: {err}"),
+        };
+    }
+
+    /// Contenu du fichier de sauvegarde
+    pub fn save(&self) -> String {
+        format!(
+            "@ puzzle\n{}\n@ progress\n{}",
+            grid_read::format(&self.puzzle),
+            grid_read::format(&self.grid),
+        )
+    }
+
+    /// Reprend une partie sauvegardée par [`Game::save`]. L'historique n'est pas conservé.
+    pub fn restore(content: &str, options: EncodingOptions) -> Result<Self, Error> {
+        let puzzles = collection::parse(content, "puzzle").map_err(Error::Collection)?;
+        let grid = |name: &'static str| match puzzles.iter().find(|p| p.name == name) {
+            Some(puzzle) => puzzle.grid.clone().map_err(Error::Grid),
+            None => Err(Error::MissingGrid(name)),
+        };
+        let puzzle = grid("puzzle")?;
+        let progress = grid("progress")?;
+
+        let consistent = puzzle.size() == progress.size()
+            && puzzle
+                .inner()
+                .iter()
+                .zip(progress.inner())
+                .all(|(given, cell)| *given == Cell::Empty || given == cell);
+        if !consistent {
+            return Err(Error::Mismatch);
+        }
+
+        Ok(Game {
+            grid: progress,
+            ..Game::new(puzzle, options)
+        })
+    }
+}
+
+/// Traduit une touche en action
+fn action(code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+    Some(match code {
+        KeyCode::Up | KeyCode::Char('k') => Action::Move(Direction::Up),
+        KeyCode::Down | KeyCode::Char('j') => Action::Move(Direction::Down),
+        KeyCode::Left | KeyCode::Char('h') => Action::Move(Direction::Left),
+        KeyCode::Right | KeyCode::Char('l') => Action::Move(Direction::Right),
+        KeyCode::Char(' ') | KeyCode::Enter => Action::Toggle,
+        KeyCode::Char('0') => Action::Set(Some(false)),
+        KeyCode::Char('1') => Action::Set(Some(true)),
+        KeyCode::Char('.') | KeyCode::Backspace | KeyCode::Delete => Action::Set(None),
+        KeyCode::Char('r') if modifiers.contains(KeyModifiers::CONTROL) => Action::Redo,
+        KeyCode::Char('u') => Action::Undo,
+        KeyCode::Char('r') => Action::Redo,
+        KeyCode::Char('?') => Action::Hint,
+        KeyCode::Char('c') => Action::Check,
+        _ => return None,
+    })
+}
+
+const HELP: &str = "flèches/hjkl: déplacer  espace: changer  0 1 .: placer  u r: annuler/refaire  \
+    ?: indice  c: vérifier  s: sauvegarder  q: quitter";
+
+fn draw(out: &mut impl Write, game: &Game, style: TermStyle) -> io::Result<()> {
+    queue!(
+        out,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(0, 0)
+    )?;
+
+    let lines = term::lines(
+        &game.puzzle,
+        Some(&game.grid),
+        game.options.rules,
+        style,
+        Some(game.cursor),
+    );
+    // En mode brut, `\n` ne ramène pas en début de ligne
+    for line in lines {
+        write!(out, "{line}\r\n")?;
+    }
+    write!(out, "\r\n{HELP}\r\n\r\n{}\r\n", game.message)?;
+
+    out.flush()
+}
+
+/// Remet le terminal en état, même si la partie s'interrompt sur une erreur
+struct RawMode;
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Lance une partie en plein écran sur `puzzle`, sauvegardée dans `save_path`. Une sauvegarde
+/// existante de la même grille est reprise.
+pub fn run(puzzle: Grid, options: EncodingOptions, save_path: &Path) -> io::Result<()> {
+    let mut game = match std::fs::read_to_string(save_path) {
+        Ok(content) => match Game::restore(&content, options) {
+            Ok(game) if game.puzzle.inner() == puzzle.inner() => {
+                let mut game = game;
+                game.message = format!("partie reprise depuis {}", save_path.display());
+                game
+            }
+            Ok(_) => {
+                let mut game = Game::new(puzzle, options);
+                game.message = "sauvegarde d'une autre grille ignorée".into();
+                game
+            }
+            Err(err) => {
+                let mut game = Game::new(puzzle, options);
+                game.message = err.to_string();
+                game
+            }
+        },
+        Err(_) => Game::new(puzzle, options),
+    };
+
+    // L'écran est toujours un terminal ici: le cadre Unicode sert aussi à montrer le curseur
+    let style = TermStyle {
+        unicode: true,
+        ..TermStyle::detect()
+    };
+
+    terminal::enable_raw_mode()?;
+    let _raw_mode = RawMode;
+    let mut out = io::stdout();
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    loop {
+        draw(&mut out, &game, style)?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Char('s') => {
+                game.message = match std::fs::write(save_path, game.save()) {
+                    Ok(()) => format!("partie sauvegardée dans {}", save_path.display()),
+                    Err(err) => format!("impossible de sauvegarder: {err}"),
+                };
+            }
+            code => {
+                if let Some(action) = action(code, key.modifiers) {
+                    game.apply(action);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inf402_takuzu::rules::RuleSet;
+
+    const PUZZLE: &str = "4\n.1.0\n..0.\n.0..\n11.0\n";
+
+    fn game() -> Game {
+        Game::new(grid_read::parse(PUZZLE).unwrap(), RuleSet::TAKUZU.into())
+    }
+
+    #[test]
+    fn moves_and_toggle() {
+        let mut game = game();
+
+        game.apply(Action::Move(Direction::Up));
+        game.apply(Action::Move(Direction::Left));
+        assert_eq!(game.cursor, (0, 0));
+
+        game.apply(Action::Toggle);
+        assert_eq!(game.grid.get(0, 0), Cell::Filled(false));
+        game.apply(Action::Toggle);
+        assert_eq!(game.grid.get(0, 0), Cell::Filled(true));
+        game.apply(Action::Toggle);
+        assert_eq!(game.grid.get(0, 0), Cell::Empty);
+
+        // Les cellules données ne bougent pas
+        game.apply(Action::Move(Direction::Right));
+        game.apply(Action::Set(Some(false)));
+        assert_eq!(game.grid.get(1, 0), Cell::Filled(true));
+        assert_eq!(game.message, "cellule donnée, non modifiable");
+
+        for _ in 0..10 {
+            game.apply(Action::Move(Direction::Down));
+        }
+        assert_eq!(game.cursor, (1, 3));
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut game = game();
+
+        game.apply(Action::Set(Some(false)));
+        game.apply(Action::Move(Direction::Down));
+        game.apply(Action::Set(Some(true)));
+
+        game.apply(Action::Undo);
+        assert_eq!(game.grid.get(0, 1), Cell::Empty);
+        game.apply(Action::Undo);
+        assert_eq!(game.grid.get(0, 0), Cell::Empty);
+        game.apply(Action::Undo);
+        assert_eq!(game.message, "rien à annuler");
+
+        game.apply(Action::Redo);
+        assert_eq!(game.grid.get(0, 0), Cell::Filled(false));
+        assert_eq!(game.cursor, (0, 0));
+
+        // Une nouvelle modification efface ce qui pouvait être refait
+        game.apply(Action::Set(Some(true)));
+        game.apply(Action::Redo);
+        assert_eq!(game.message, "rien à refaire");
+        assert_eq!(game.grid.get(0, 1), Cell::Empty);
+    }
+
+    #[test]
+    fn hints_solve_the_grid() {
+        let mut game = game();
+
+        game.apply(Action::Hint);
+        assert!(game.message.starts_with("indice: "), "{}", game.message);
+
+        for _ in 0..16 {
+            game.apply(Action::Hint);
+        }
+        assert!(game.is_solved());
+        assert_eq!(grid_read::format(&game.grid), "4\n0110\n1001\n0011\n1100\n");
+    }
+
+    /// Avec plusieurs solutions, seule une cellule dont la valeur est prouvée est remplie
+    #[test]
+    fn hints_with_several_solutions() {
+        let mut game = Game::new(Grid::new(4), RuleSet::TAKUZU.into());
+        game.apply(Action::Hint);
+        assert_eq!(
+            game.message,
+            "plusieurs solutions: aucune cellule vide n'a de valeur imposée"
+        );
+        assert_eq!(game.grid.inner(), Grid::new(4).inner());
+
+        // Sans déduction simple, (0, 1) vaut 0 dans toutes les solutions, qui ne sont pas uniques
+        let grid = grid_read::parse("4\n...0\n...1\n10..\n..1.\n").unwrap();
+        let mut game = Game::new(grid, RuleSet::TAKUZU.into());
+        game.apply(Action::Hint);
+        assert_eq!(game.message, "indice (solveur): (0, 1) vaut 0");
+        assert_eq!(game.grid.get(0, 1), Cell::Filled(false));
+    }
+
+    #[test]
+    fn check() {
+        let mut game = game();
+
+        game.apply(Action::Check);
+        assert_eq!(game.message, "pas d'erreur pour l'instant");
+
+        // (0, 0) vaut 0 dans l'unique solution
+        game.apply(Action::Set(Some(true)));
+        game.apply(Action::Check);
+        assert_eq!(
+            game.message,
+            "impasse: au moins une valeur placée est fausse"
+        );
+    }
+
+    #[test]
+    fn save_restore() {
+        let mut game = game();
+        game.apply(Action::Set(Some(false)));
+
+        let saved = game.save();
+        assert!(saved.starts_with("@ puzzle\n4\n"));
+
+        let restored = Game::restore(&saved, RuleSet::TAKUZU.into()).unwrap();
+        assert_eq!(restored.puzzle.inner(), game.puzzle.inner());
+        assert_eq!(restored.grid.inner(), game.grid.inner());
+
+        assert!(matches!(
+            Game::restore(
+                "@ puzzle\n4\n....\n....\n....\n....\n",
+                RuleSet::TAKUZU.into()
+            ),
+            Err(Error::MissingGrid("progress")),
+        ));
+        let changed_given = saved.replace("@ progress\n4\n01.0", "@ progress\n4\n00.0");
+        assert!(matches!(
+            Game::restore(&changed_given, RuleSet::TAKUZU.into()),
+            Err(Error::Mismatch),
+        ));
+    }
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            action(KeyCode::Char('1'), KeyModifiers::NONE),
+            Some(Action::Set(Some(true)))
+        );
+        assert_eq!(
+            action(KeyCode::Char('r'), KeyModifiers::CONTROL),
+            Some(Action::Redo)
+        );
+        assert_eq!(action(KeyCode::Char('x'), KeyModifiers::NONE), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Suivi de l'avancement des étapes longues (encodage, résolution, lecture).
+//!
+//! Les fonctions de la bibliothèque n'écrivent rien elles-mêmes: elles envoient des [`Event`] à
+//! un [`Progress`], qui les affiche ou non. [`Silent`] les ignore, [`Bar`] dessine une barre de
+//! progression pour un terminal, [`Text`] écrit une ligne par étape (journaux, tubes) et
+//! [`JsonLines`] un objet JSON par évènement, pour les outils d'intégration continue.
+use serde::{Serialize, Serializer};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Évènement émis pendant une étape, identifiée par son nom (par exemple `rule 3`)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event<'a> {
+    /// Début d'une étape
+    Start { step: &'a str },
+
+    /// Avancement d'une étape: `done` sur `total`
+    Advance {
+        step: &'a str,
+        done: usize,
+        total: usize,
+    },
+
+    /// Fin d'une étape
+    Finish {
+        step: &'a str,
+        #[serde(rename = "ms", serialize_with = "milliseconds")]
+        duration: Duration,
+    },
+
+    /// Message libre, hors de toute étape
+    Message { text: &'a str },
+}
+
+/// Sérialise une durée en millisecondes, en nombre flottant (champs `ms` des sorties JSON)
+pub(crate) fn milliseconds<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+/// Destinataire des évènements d'avancement
+pub trait Progress {
+    fn event(&mut self, event: Event<'_>);
+}
+
+impl dyn Progress + '_ {
+    /// Exécute `f` comme l'étape `step`, encadrée de ses évènements de début et de fin, et
+    /// renvoie son résultat avec sa durée
+    pub fn step<T>(&mut self, step: &str, f: impl FnOnce(&mut dyn Progress) -> T) -> (T, Duration) {
+        self.event(Event::Start { step });
+        let start = Instant::now();
+        let result = f(self);
+        let duration = start.elapsed();
+        self.event(Event::Finish { step, duration });
+        (result, duration)
+    }
+}
+
+/// Ignore tous les évènements: comportement par défaut de la bibliothèque
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Silent;
+
+impl Progress for Silent {
+    fn event(&mut self, _: Event<'_>) {}
+}
+
+/// Une ligne par début et fin d'étape; l'avancement n'est pas affiché
+pub struct Text<W>(pub W);
+
+impl<W: Write> Progress for Text<W> {
+    fn event(&mut self, event: Event<'_>) {
+        // Les erreurs d'écriture de l'avancement ne doivent pas interrompre le travail
+        let _ = match event {
+            Event::Start { step } => writeln!(self.0, "[{step}] starting"),
+            Event::Advance { .. } => Ok(()),
+            Event::Finish { step, duration } => writeln!(self.0, "[{step}] DONE ({duration:?})"),
+            Event::Message { text } => writeln!(self.0, "{text}"),
+        };
+    }
+}
+
+/// Barre de progression pour un terminal, redessinée sur place à chaque avancement
+pub struct Bar<W> {
+    out: W,
+
+    /// La dernière ligne écrite n'est pas terminée
+    pending: bool,
+}
+
+impl<W> Bar<W> {
+    const WIDTH: usize = 20;
+
+    pub fn new(out: W) -> Self {
+        Bar {
+            out,
+            pending: false,
+        }
+    }
+}
+
+impl<W: Write> Progress for Bar<W> {
+    fn event(&mut self, event: Event<'_>) {
+        // `\r` revient en début de ligne, `\x1b[K` efface la fin de la ligne
+        let _ = match event {
+            Event::Start { step } => {
+                if self.pending {
+                    let _ = writeln!(self.out);
+                }
+                self.pending = true;
+                write!(self.out, "[{step}] ...")
+            }
+            Event::Advance { step, done, total } => {
+                let filled = (done * Self::WIDTH).checked_div(total).unwrap_or(0);
+                self.pending = true;
+                write!(
+                    self.out,
+                    "\r\x1b[K[{step}] [{}{}] {done}/{total}",
+                    "#".repeat(filled.min(Self::WIDTH)),
+                    ".".repeat(Self::WIDTH - filled.min(Self::WIDTH)),
+                )
+            }
+            Event::Finish { step, duration } => {
+                let start = if self.pending { "\r\x1b[K" } else { "" };
+                self.pending = false;
+                writeln!(self.out, "{start}[{step}] DONE ({duration:?})")
+            }
+            Event::Message { text } => {
+                let start = if self.pending { "\n" } else { "" };
+                self.pending = false;
+                writeln!(self.out, "{start}{text}")
+            }
+        };
+        let _ = self.out.flush();
+    }
+}
+
+/// Un objet JSON par ligne et par évènement, par exemple
+/// `{"event":"finish","step":"rule 1","ms":0.08}`
+pub struct JsonLines<W>(pub W);
+
+impl<W: Write> Progress for JsonLines<W> {
+    fn event(&mut self, event: Event<'_>) {
+        if serde_json::to_writer(&mut self.0, &event).is_ok() {
+            let _ = writeln!(self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(progress: &mut dyn Progress) {
+        progress.event(Event::Start { step: "rule 3" });
+        progress.event(Event::Advance {
+            step: "rule 3",
+            done: 1,
+            total: 2,
+        });
+        progress.event(Event::Finish {
+            step: "rule 3",
+            duration: Duration::from_millis(2),
+        });
+        progress.event(Event::Message { text: "fini" });
+    }
+
+    #[test]
+    fn text() {
+        let mut text = Text(Vec::new());
+        scenario(&mut text);
+        assert_eq!(
+            String::from_utf8(text.0).unwrap(),
+            "[rule 3] starting\n[rule 3] DONE (2ms)\nfini\n"
+        );
+    }
+
+    #[test]
+    fn bar() {
+        let mut bar = Bar::new(Vec::new());
+        scenario(&mut bar);
+        assert_eq!(
+            String::from_utf8(bar.out).unwrap(),
+            "[rule 3] ...\r\x1b[K[rule 3] [##########..........] 1/2\
+            \r\x1b[K[rule 3] DONE (2ms)\nfini\n"
+        );
+    }
+
+    #[test]
+    fn json_lines() {
+        let mut json = JsonLines(Vec::new());
+        scenario(&mut json);
+        let lines: Vec<serde_json::Value> = String::from_utf8(json.0)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["event"], "start");
+        assert_eq!(lines[1]["done"], 1);
+        assert_eq!(lines[2]["ms"], 2.0);
+        assert_eq!(lines[3]["text"], "fini");
+    }
+
+    #[test]
+    fn step() {
+        let mut text = Text(Vec::new());
+        let progress: &mut dyn Progress = &mut text;
+        let (value, _) = progress.step("encoding", |_| 42);
+
+        assert_eq!(value, 42);
+        let out = String::from_utf8(text.0).unwrap();
+        assert!(out.starts_with("[encoding] starting\n[encoding] DONE ("));
+    }
+}
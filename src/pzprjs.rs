@@ -0,0 +1,193 @@
+//! URL pzprjs (puzz.link, pzv.jp) des grilles « binary », le nom du Takuzu dans pzprjs.
+//!
+//! Une URL est de la forme `https://puzz.link/p?binary/<colonnes>/<rangées>/<corps>`. Le corps
+//! parcourt les cellules ligne par ligne: `0` et `1` placent un chiffre, `.` marque une cellule
+//! « ? » (traitée comme vide), et une lettre de `a` à `z` saute de 1 à 26 cellules vides.
+//! Seul le texte après le `?` est analysé, l'hôte n'a donc pas d'importance.
+
+use crate::{Cell, Grid};
+use std::fmt::{Display, Formatter};
+
+const PREFIX: &str = "https://puzz.link/p?";
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Error {
+    /// Le type de grille n'est pas `binary`
+    UnsupportedPuzzle(String),
+
+    /// Les dimensions sont absentes, invalides ou impaires
+    InvalidSize(String),
+
+    /// Les grilles non carrées ne sont pas prises en charge
+    NotSquare { width: usize, height: usize },
+
+    /// Le corps contient un caractère qui n'a pas de sens pour une grille binaire
+    InvalidChar(char),
+
+    /// Le corps décrit plus de cellules que la grille n'en contient
+    TooLong,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnsupportedPuzzle(kind) => {
+                write!(f, "type de grille non pris en charge: {kind:?}")
+            }
+            Error::InvalidSize(size) => write!(f, "dimensions invalides: {size:?}"),
+            Error::NotSquare { width, height } => {
+                write!(f, "grille {width}x{height} non carrée")
+            }
+            Error::InvalidChar(c) => write!(f, "caractère {c:?} inattendu"),
+            Error::TooLong => write!(f, "le corps décrit trop de cellules"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Lis une URL pzprjs (ou seulement sa partie après le `?`)
+pub fn parse(url: &str) -> Result<Grid, Error> {
+    let url = url.trim();
+    let query = url.split_once('?').map_or(url, |(_, query)| query);
+
+    let mut parts = query.splitn(4, '/');
+    let kind = parts.next().unwrap_or_default();
+    if kind != "binary" {
+        return Err(Error::UnsupportedPuzzle(kind.into()));
+    }
+
+    let mut dimension = || {
+        let part = parts.next().unwrap_or_default();
+        match part.parse::<usize>() {
+            Ok(n) if n != 0 && n % 2 == 0 => Ok(n),
+            _ => Err(Error::InvalidSize(part.into())),
+        }
+    };
+    let width = dimension()?;
+    let height = dimension()?;
+    if width != height {
+        return Err(Error::NotSquare { width, height });
+    }
+    let body = parts.next().unwrap_or_default();
+
+    let size = width;
+    let cells = size * size;
+    let mut grid = Grid::new(size);
+    let mut pos = 0;
+
+    for c in body.chars() {
+        if pos >= cells {
+            return Err(Error::TooLong);
+        }
+
+        match c {
+            '0' | '1' => grid.set(pos % size, pos / size, c == '1'),
+            '.' => (),
+            'a'..='z' => pos += c as usize - 'a' as usize,
+            c => return Err(Error::InvalidChar(c)),
+        }
+        pos += 1;
+    }
+
+    if pos > cells {
+        return Err(Error::TooLong);
+    }
+
+    Ok(grid)
+}
+
+/// Produit l'URL puzz.link de la grille
+pub fn export(grid: &Grid) -> String {
+    let mut url = format!("{PREFIX}binary/{0}/{0}/", grid.size());
+
+    let mut run = 0;
+    let flush = |url: &mut String, run: &mut u8| {
+        if *run > 0 {
+            url.push((b'a' + *run - 1) as char);
+            *run = 0;
+        }
+    };
+
+    for cell in grid.inner() {
+        match *cell {
+            Cell::Empty => {
+                run += 1;
+                if run == 26 {
+                    flush(&mut url, &mut run);
+                }
+            }
+            Cell::Filled(value) => {
+                flush(&mut url, &mut run);
+                url.push(if value { '1' } else { '0' });
+            }
+        }
+    }
+    flush(&mut url, &mut run);
+
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_read;
+
+    const GRID: &str = "4\n.1.0\n..0.\n.0..\n11.0\n";
+
+    #[test]
+    fn round_trip() {
+        let grid = grid_read::parse(GRID).unwrap();
+
+        let url = export(&grid);
+        assert_eq!(url, "https://puzz.link/p?binary/4/4/a1a0b0b0b11a0");
+
+        assert_eq!(grid_read::format(&parse(&url).unwrap()), GRID);
+        assert_eq!(
+            grid_read::format(&parse("http://pzv.jp/p.html?binary/4/4/a1a0b0b0b11a0").unwrap()),
+            GRID,
+        );
+        assert_eq!(
+            grid_read::format(&parse("binary/4/4/a1a0b0b0b11a0").unwrap()),
+            GRID,
+        );
+    }
+
+    #[test]
+    fn long_runs() {
+        let mut grid = Grid::new(8);
+        grid.set(6, 7, true);
+
+        let url = export(&grid);
+        assert_eq!(url, "https://puzz.link/p?binary/8/8/zzj1a");
+        assert_eq!(parse(&url).unwrap().inner(), grid.inner());
+
+        // Le corps peut s'arrêter avant la fin de la grille
+        assert_eq!(parse("binary/8/8/zzj1").unwrap().inner(), grid.inner());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("https://puzz.link/p?nurikabe/4/4/").unwrap_err(),
+            Error::UnsupportedPuzzle("nurikabe".into()),
+        );
+        assert_eq!(
+            parse("binary/5/5/").unwrap_err(),
+            Error::InvalidSize("5".into()),
+        );
+        assert_eq!(
+            parse("binary/4").unwrap_err(),
+            Error::InvalidSize("".into()),
+        );
+        assert_eq!(
+            parse("binary/4/6/").unwrap_err(),
+            Error::NotSquare {
+                width: 4,
+                height: 6,
+            },
+        );
+        assert_eq!(parse("binary/4/4/2").unwrap_err(), Error::InvalidChar('2'));
+        assert_eq!(parse("binary/2/2/d0").unwrap_err(), Error::TooLong);
+    }
+}
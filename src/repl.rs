@@ -0,0 +1,620 @@
+//! Session interactive, ligne par ligne, pour explorer une grille en cours de résolution.
+//!
+//! La formule des règles est construite une seule fois à partir de la grille de départ et donnée au
+//! solveur choisi (`varisat` par défaut, qui est incrémental). Les cellules placées pendant la
+//! session ne modifient pas la formule: elles sont passées au solveur comme hypothèses, ce qui
+//! permet aussi de demander au solveur lesquelles expliquent une déduction (`why`).
+
+use crate::term::{self, TermStyle};
+use inf402_takuzu::backend::{self, Backend, SatBackend};
+use inf402_takuzu::cnf::CNFFile;
+use inf402_takuzu::human;
+use inf402_takuzu::progress::Silent;
+use inf402_takuzu::rules::{self, EncodingOptions, RuleSet, RuleStats};
+use inf402_takuzu::solve::model_to_grid;
+use inf402_takuzu::validate;
+use inf402_takuzu::{Cell, Grid};
+use std::fmt::{Display, Formatter, Write as _};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::str::FromStr;
+use varisat::Lit;
+
+const HELP: &str = "\
+commandes:
+  set <x> <y> <0|1>   place une valeur
+  clear <x> <y>       vide une cellule
+  show                affiche la grille
+  solve               affiche une solution de la grille actuelle
+  count [max]         compte les solutions (au plus `max`, 1000 par défaut)
+  hint                donne une déduction
+  why <x> <y>         explique la valeur forcée d'une cellule
+  cnf stats           taille de la formule
+  help                affiche cette aide
+  quit                termine la session
+";
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownCommand(String),
+
+    /// Arguments manquants ou en trop; contient l'usage de la commande
+    Usage(&'static str),
+
+    InvalidNumber(String),
+
+    OutOfBounds {
+        x: usize,
+        y: usize,
+    },
+
+    /// Les cellules données font partie de la formule et ne peuvent pas être modifiées
+    GivenCell {
+        x: usize,
+        y: usize,
+    },
+
+    Solver(backend::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnknownCommand(command) => {
+                write!(f, "commande inconnue {command:?} (voir `help`)")
+            }
+            Error::Usage(usage) => write!(f, "usage: {usage}"),
+            Error::InvalidNumber(word) => write!(f, "nombre invalide: {word:?}"),
+            Error::OutOfBounds { x, y } => write!(f, "({x}, {y}) est hors de la grille"),
+            Error::GivenCell { x, y } => {
+                write!(f, "({x}, {y}) est une cellule donnée, non modifiable")
+            }
+            Error::Solver(err) => write!(f, "erreur du solveur: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Self {
+        Error::Solver(err)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Command {
+    Set(usize, usize, bool),
+    Clear(usize, usize),
+    Show,
+    Solve,
+    Count(usize),
+    Hint,
+    Why(usize, usize),
+    CnfStats,
+    Help,
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        fn number(word: &str) -> Result<usize, Error> {
+            word.parse().map_err(|_| Error::InvalidNumber(word.into()))
+        }
+
+        let words: Vec<_> = line.split_whitespace().collect();
+        let command = match words.as_slice() {
+            ["set", x, y, value] => Command::Set(
+                number(x)?,
+                number(y)?,
+                match *value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(Error::Usage("set <x> <y> <0|1>")),
+                },
+            ),
+            ["set", ..] => return Err(Error::Usage("set <x> <y> <0|1>")),
+            ["clear", x, y] => Command::Clear(number(x)?, number(y)?),
+            ["clear", ..] => return Err(Error::Usage("clear <x> <y>")),
+            ["show"] => Command::Show,
+            ["solve"] => Command::Solve,
+            ["count"] => Command::Count(1000),
+            ["count", max] => match number(max)? {
+                0 => return Err(Error::Usage("count [max], avec max > 0")),
+                max => Command::Count(max),
+            },
+            ["count", ..] => return Err(Error::Usage("count [max], avec max > 0")),
+            ["hint"] => Command::Hint,
+            ["why", x, y] => Command::Why(number(x)?, number(y)?),
+            ["why", ..] => return Err(Error::Usage("why <x> <y>")),
+            ["cnf", "stats"] => Command::CnfStats,
+            ["cnf", ..] => return Err(Error::Usage("cnf stats")),
+            ["help"] => Command::Help,
+            ["quit"] | ["exit"] => Command::Quit,
+            _ => return Err(Error::UnknownCommand(line.into())),
+        };
+
+        Ok(command)
+    }
+}
+
+/// Cellules placées qui excluent une valeur, `None` si la valeur reste possible
+type Exclusion = Option<Vec<(usize, usize)>>;
+
+/// État d'une session
+pub struct Session {
+    /// Grille de départ, encodée dans la formule
+    puzzle: Grid,
+
+    /// Grille actuelle, qui contient les cellules données
+    grid: Grid,
+
+    rules: RuleSet,
+    style: TermStyle,
+    solver: Box<dyn SatBackend>,
+
+    /// Prochaine variable libre, pour les variables d'activation
+    next_var: usize,
+
+    /// Nombre de variables auxiliaires de l'encodage
+    aux_count: usize,
+
+    /// Taille de la formule: statistiques par règle, puis nombre total de clauses et de littéraux
+    stats: Vec<RuleStats>,
+    clauses: usize,
+    literals: usize,
+}
+
+impl Session {
+    pub fn new(
+        puzzle: Grid,
+        options: &EncodingOptions,
+        style: TermStyle,
+        backend: &Backend,
+    ) -> Self {
+        let mut cnf = CNFFile::new_varisat(&puzzle);
+        let stats = rules::write_rules(&mut cnf, &puzzle, options, &mut Silent);
+        let (clauses, literals) = (cnf.len(), cnf.literal_count());
+        let (next_var, aux_count) = (cnf.variable_count(), cnf.aux_count());
+
+        let mut solver = backend.create();
+        solver.add_formula(&cnf.into_varisat());
+
+        Session {
+            grid: puzzle.clone(),
+            puzzle,
+            rules: options.rules,
+            style,
+            solver,
+            next_var,
+            aux_count,
+            stats,
+            clauses,
+            literals,
+        }
+    }
+
+    fn check_bounds(&self, x: usize, y: usize) -> Result<(), Error> {
+        if x >= self.grid.size() || y >= self.grid.size() {
+            Err(Error::OutOfBounds { x, y })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn lit(&self, x: usize, y: usize, value: bool) -> Lit {
+        Lit::from_index(y * self.grid.size() + x, value)
+    }
+
+    /// Hypothèses correspondant aux cellules placées pendant la session, sauf `except`
+    fn assumptions(&self, except: Option<(usize, usize)>) -> Vec<Lit> {
+        let size = self.grid.size();
+        (0..size * size)
+            .map(|i| (i % size, i / size))
+            .filter(|&cell| Some(cell) != except && self.puzzle.get(cell.0, cell.1) == Cell::Empty)
+            .filter_map(|(x, y)| match self.grid.get(x, y) {
+                Cell::Filled(value) => Some(self.lit(x, y, value)),
+                Cell::Empty => None,
+            })
+            .collect()
+    }
+
+    /// Jusqu'à `limit` solutions distinctes de la grille actuelle. Les clauses bloquantes sont
+    /// conditionnées par une variable d'activation, désactivée ensuite pour ne pas gêner les
+    /// commandes suivantes.
+    fn solutions(&mut self, limit: usize) -> Result<Vec<Grid>, Error> {
+        let size = self.grid.size();
+        let activation = Lit::from_index(self.next_var, true);
+        self.next_var += 1;
+        let mut assumptions = self.assumptions(None);
+        assumptions.push(activation);
+
+        let mut solutions = Vec::new();
+        while solutions.len() < limit {
+            self.solver.assume(&assumptions);
+            if !self.solver.solve()? {
+                break;
+            }
+
+            let solution = model_to_grid(size, &self.solver.model().unwrap());
+            let mut blocking: Vec<_> = (0..size * size)
+                .map(|i| match solution.inner()[i] {
+                    Cell::Filled(value) => Lit::from_index(i, !value),
+                    Cell::Empty => unreachable!(),
+                })
+                .collect();
+            blocking.push(!activation);
+            self.solver.add_clause(&blocking);
+            solutions.push(solution);
+        }
+
+        self.solver.add_clause(&[!activation]);
+        Ok(solutions)
+    }
+
+    /// Pour chacune des valeurs 0 et 1 de la cellule `(x, y)`, compte tenu des autres cellules:
+    /// `None` si elle est possible, sinon les cellules placées qui l'excluent
+    fn exclusions(&mut self, x: usize, y: usize) -> Result<[Exclusion; 2], Error> {
+        let base = self.assumptions(Some((x, y)));
+        let size = self.grid.size();
+
+        let mut result = [None, None];
+        for (value, core) in [false, true].iter().zip(result.iter_mut()) {
+            let lit = self.lit(x, y, *value);
+            let mut assumptions = base.clone();
+            assumptions.push(lit);
+            self.solver.assume(&assumptions);
+
+            if !self.solver.solve()? {
+                let mut cells: Vec<_> = self
+                    .solver
+                    .failed_core()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|&&l| l != lit)
+                    .map(|l| (l.var().index() % size, l.var().index() / size))
+                    .collect();
+                cells.sort_by_key(|&(x, y)| (y, x));
+                *core = Some(cells);
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn why(&mut self, x: usize, y: usize) -> Result<String, Error> {
+        if let Cell::Filled(value) = self.puzzle.get(x, y) {
+            return Ok(format!(
+                "({x}, {y}) vaut {} (cellule donnée)\n",
+                value as u8
+            ));
+        }
+
+        let [zero, one] = self.exclusions(x, y)?;
+        let (value, core) = match (zero, one) {
+            (None, None) => {
+                return Ok(format!(
+                    "({x}, {y}) n'est pas forcée: les deux valeurs sont encore possibles\n"
+                ))
+            }
+            (Some(_), Some(_)) => return Ok("la grille actuelle n'a aucune solution\n".into()),
+            (None, Some(core)) => (false, core),
+            (Some(core), None) => (true, core),
+        };
+
+        let mut text = format!(
+            "({x}, {y}) vaut {}: {} est impossible avec les cellules données",
+            value as u8, !value as u8,
+        );
+        if !core.is_empty() {
+            let cells: Vec<_> = core
+                .iter()
+                .map(|&(x, y)| match self.grid.get(x, y) {
+                    Cell::Filled(value) => format!("({x}, {y})={}", value as u8),
+                    Cell::Empty => format!("({x}, {y})"),
+                })
+                .collect();
+            write!(text, " et {}", cells.join(", ")).unwrap();
+        }
+        text.push('\n');
+
+        Ok(text)
+    }
+
+    fn hint(&mut self) -> Result<String, Error> {
+        let conflicts = validate::conflicts(&self.grid, self.rules);
+        if let Some(&(x, y)) = conflicts.iter().min_by_key(|&&(x, y)| (y, x)) {
+            return Ok(format!(
+                "la grille enfreint déjà les règles, par exemple en ({x}, {y})\n"
+            ));
+        }
+
+        if let Some(deduction) = human::next_deduction(&self.grid, self.rules) {
+            return Ok(format!("indice: {deduction}\n"));
+        }
+
+        let size = self.grid.size();
+        for (x, y) in (0..size * size).map(|i| (i % size, i / size)) {
+            if self.grid.get(x, y) != Cell::Empty {
+                continue;
+            }
+
+            match self.exclusions(x, y)? {
+                [Some(_), Some(_)] => return Ok("la grille actuelle n'a aucune solution\n".into()),
+                [None, Some(_)] => return Ok(format!("indice (solveur): ({x}, {y}) vaut 0\n")),
+                [Some(_), None] => return Ok(format!("indice (solveur): ({x}, {y}) vaut 1\n")),
+                _ => (),
+            }
+        }
+
+        Ok("aucune cellule n'est forcée: la grille a plusieurs solutions\n".into())
+    }
+
+    fn cnf_stats(&self) -> String {
+        let size = self.grid.size();
+        let givens = self.puzzle.to_literals().len();
+
+        let mut text = format!(
+            "variables: {}{}\nclauses: {} ({} littéraux)\n  cellules données: {givens}\n",
+            size * size + self.aux_count,
+            match self.aux_count {
+                0 => String::new(),
+                aux => format!(" (dont {aux} auxiliaires)"),
+            },
+            self.clauses,
+            self.literals,
+        );
+        for s in &self.stats {
+            writeln!(
+                text,
+                "  règle {}: {} clauses, {} littéraux",
+                s.rule, s.clauses, s.literals,
+            )
+            .unwrap();
+        }
+
+        text
+    }
+
+    /// Exécute une commande et renvoie le texte à afficher
+    pub fn execute(&mut self, command: Command) -> Result<String, Error> {
+        if let Command::Set(x, y, _) | Command::Clear(x, y) = command {
+            self.check_bounds(x, y)?;
+            if self.puzzle.get(x, y) != Cell::Empty {
+                return Err(Error::GivenCell { x, y });
+            }
+        }
+
+        let text = match command {
+            Command::Set(x, y, value) => {
+                self.grid.set(x, y, value);
+                String::new()
+            }
+            Command::Clear(x, y) => {
+                self.grid.set_cell(x, y, Cell::Empty);
+                String::new()
+            }
+            Command::Show => term::render(&self.puzzle, Some(&self.grid), self.rules, self.style),
+            Command::Solve => match self.solutions(2)?.as_slice() {
+                [] => "aucune solution\n".into(),
+                [solution] => format!(
+                    "solution unique:\n{}",
+                    term::render(&self.grid, Some(solution), self.rules, self.style)
+                ),
+                [solution, ..] => format!(
+                    "une solution parmi plusieurs:\n{}",
+                    term::render(&self.grid, Some(solution), self.rules, self.style)
+                ),
+            },
+            Command::Count(limit) => {
+                let count = self.solutions(limit)?.len();
+                if count == limit {
+                    format!("au moins {count} solution(s)\n")
+                } else {
+                    format!("{count} solution(s)\n")
+                }
+            }
+            Command::Hint => self.hint()?,
+            Command::Why(x, y) => {
+                self.check_bounds(x, y)?;
+                self.why(x, y)?
+            }
+            Command::CnfStats => self.cnf_stats(),
+            Command::Help => HELP.into(),
+            Command::Quit => String::new(),
+        };
+
+        Ok(text)
+    }
+}
+
+/// Lit les commandes de `input` jusqu'à `quit` ou la fin de l'entrée. Les lignes vides et les
+/// commentaires (`#`) sont ignorés. Avec `echo`, chaque commande est recopiée avant son résultat,
+/// pour rejouer un script de démonstration.
+pub fn run(session: &mut Session, mut input: impl BufRead, echo: bool) -> io::Result<()> {
+    let prompt = !echo && io::stdin().is_terminal();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut line = String::new();
+
+    loop {
+        if prompt {
+            write!(out, "> ")?;
+            out.flush()?;
+        }
+
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if echo {
+            writeln!(out, "> {line}")?;
+        }
+
+        match line.parse().and_then(|command| match command {
+            Command::Quit => Ok(None),
+            command => session.execute(command).map(Some),
+        }) {
+            Ok(Some(text)) => write!(out, "{text}")?,
+            Ok(None) => return Ok(()),
+            Err(err) => writeln!(out, "erreur: {err}")?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inf402_takuzu::grid_read;
+    use inf402_takuzu::rules::{BalanceEncoding, UniquenessEncoding};
+
+    fn session() -> Session {
+        let puzzle = grid_read::parse("4\n.1.0\n..0.\n.0..\n11.0\n").unwrap();
+        Session::new(
+            puzzle,
+            &EncodingOptions::default(),
+            TermStyle::PLAIN,
+            &Backend::Varisat,
+        )
+    }
+
+    fn run(session: &mut Session, line: &str) -> String {
+        match session.execute(line.parse().unwrap()) {
+            Ok(text) => text,
+            Err(err) => format!("erreur: {err}"),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "set 1 2 1".parse::<Command>().unwrap(),
+            Command::Set(1, 2, true)
+        );
+        assert_eq!("count".parse::<Command>().unwrap(), Command::Count(1000));
+        assert_eq!("count 5".parse::<Command>().unwrap(), Command::Count(5));
+        assert!(matches!("count 0".parse::<Command>(), Err(Error::Usage(_))));
+        assert_eq!("cnf stats".parse::<Command>().unwrap(), Command::CnfStats);
+        assert!(matches!("set 1 2".parse::<Command>(), Err(Error::Usage(_))));
+        assert!(matches!(
+            "why a 2".parse::<Command>(),
+            Err(Error::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "dance".parse::<Command>(),
+            Err(Error::UnknownCommand(_))
+        ));
+    }
+
+    #[test]
+    fn set_clear_show() {
+        let mut session = session();
+
+        assert_eq!(run(&mut session, "set 0 0 0"), "");
+        assert_eq!(run(&mut session, "show"), "01.0\n..0.\n.0..\n11.0\n");
+        assert_eq!(run(&mut session, "clear 0 0"), "");
+        assert_eq!(run(&mut session, "show"), ".1.0\n..0.\n.0..\n11.0\n");
+
+        assert_eq!(
+            run(&mut session, "set 1 0 0"),
+            "erreur: (1, 0) est une cellule donnée, non modifiable",
+        );
+        assert_eq!(
+            run(&mut session, "clear 4 0"),
+            "erreur: (4, 0) est hors de la grille",
+        );
+    }
+
+    #[test]
+    fn solve_and_count() {
+        let mut session = session();
+
+        assert_eq!(
+            run(&mut session, "solve"),
+            "solution unique:\n0110\n1001\n0011\n1100\n",
+        );
+        assert_eq!(run(&mut session, "count"), "1 solution(s)\n");
+
+        // Les clauses bloquantes d'un comptage ne restent pas actives
+        assert_eq!(run(&mut session, "count"), "1 solution(s)\n");
+
+        run(&mut session, "set 0 0 1");
+        assert_eq!(run(&mut session, "solve"), "aucune solution\n");
+        assert_eq!(run(&mut session, "count"), "0 solution(s)\n");
+
+        let mut empty = Session::new(
+            Grid::new(4),
+            &EncodingOptions::default(),
+            TermStyle::PLAIN,
+            &Backend::Varisat,
+        );
+        assert_eq!(run(&mut empty, "count"), "72 solution(s)\n");
+        assert_eq!(run(&mut empty, "count 10"), "au moins 10 solution(s)\n");
+
+        // Les variables auxiliaires ne changent pas le nombre de solutions
+        let options = EncodingOptions {
+            balance: BalanceEncoding::Sequential,
+            uniqueness: UniquenessEncoding::Tseitin,
+            ..EncodingOptions::default()
+        };
+        let mut aux = Session::new(Grid::new(4), &options, TermStyle::PLAIN, &Backend::Varisat);
+        assert_eq!(run(&mut aux, "count"), "72 solution(s)\n");
+        assert!(run(&mut aux, "cnf stats").starts_with("variables: 160 (dont 144 auxiliaires)\n"));
+        assert!(run(&mut empty, "solve").starts_with("une solution parmi plusieurs:\n"));
+    }
+
+    #[test]
+    fn why() {
+        let mut session = session();
+
+        assert_eq!(
+            run(&mut session, "why 1 0"),
+            "(1, 0) vaut 1 (cellule donnée)\n"
+        );
+        assert_eq!(
+            run(&mut session, "why 0 0"),
+            "(0, 0) vaut 0: 1 est impossible avec les cellules données\n",
+        );
+
+        let mut empty = Session::new(
+            Grid::new(4),
+            &EncodingOptions::default(),
+            TermStyle::PLAIN,
+            &Backend::Varisat,
+        );
+        assert_eq!(
+            run(&mut empty, "why 2 0"),
+            "(2, 0) n'est pas forcée: les deux valeurs sont encore possibles\n",
+        );
+        run(&mut empty, "set 0 0 1");
+        run(&mut empty, "set 1 0 1");
+        assert_eq!(
+            run(&mut empty, "why 2 0"),
+            "(2, 0) vaut 0: 1 est impossible avec les cellules données et (0, 0)=1, (1, 0)=1\n",
+        );
+    }
+
+    #[test]
+    fn hint_and_stats() {
+        let mut session = session();
+
+        assert!(run(&mut session, "hint").starts_with("indice: "));
+
+        run(&mut session, "set 0 0 1");
+        run(&mut session, "set 0 1 1");
+        assert_eq!(
+            run(&mut session, "hint"),
+            "la grille enfreint déjà les règles, par exemple en (0, 0)\n",
+        );
+
+        let stats = run(&mut session, "cnf stats");
+        assert!(stats.starts_with("variables: 16\n"));
+        assert!(stats.contains("  cellules données: 7\n"));
+        assert!(stats.contains("  règle 3: "));
+    }
+}
@@ -1,7 +1,14 @@
+//! Encodage des règles du Takuzu en clauses CNF
 use crate::cnf::{CNFFile, Literal};
 use crate::logic_utils::dnf_to_cnf;
+use crate::progress::{self, Event, Progress, Silent};
 use crate::Grid;
-use std::time::Instant;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use varisat::CnfFormula;
 
 /// Credits: <https://docs.python.org/3.9/library/itertools.html#itertools.combinations>
 fn combinations<T>(list: &[T], r: usize) -> Vec<Vec<&T>> {
@@ -39,42 +46,91 @@ fn combinations<T>(list: &[T], r: usize) -> Vec<Vec<&T>> {
     }
 }
 
+/// Règle 1 par combinaisons. Les clauses de chaque rangée (et de la colonne de même indice) sont
+/// générées en parallèle, puis ajoutées dans l'ordre des rangées: la formule ne dépend pas du
+/// nombre de threads.
 pub fn write_rule_1<W>(out: &mut CNFFile<W>, grid: &Grid) {
-    for k in 0..grid.size {
-        let row_or_line: Vec<_> = std::iter::repeat(k).enumerate().take(grid.size).collect();
+    out.push_batches((0..grid.size()).into_par_iter().map(|k| {
+        let row_or_line: Vec<_> = std::iter::repeat(k).enumerate().take(grid.size()).collect();
+        let mut clauses = Vec::new();
 
-        for combination in combinations(&row_or_line, grid.size / 2 + 1) {
-            out.push(
+        for combination in combinations(&row_or_line, grid.size() / 2 + 1) {
+            clauses.push(
                 combination
                     .iter()
                     .map(|(k, l)| Literal::new(*k, *l, true))
                     .collect(),
             );
-            out.push(
+            clauses.push(
                 combination
                     .iter()
                     .map(|(k, l)| Literal::new(*k, *l, false))
                     .collect(),
             );
-            out.push(
+            clauses.push(
                 combination
                     .iter()
                     .map(|(k, l)| Literal::new(*l, *k, true))
                     .collect(),
             );
-            out.push(
+            clauses.push(
                 combination
                     .iter()
                     .map(|(k, l)| Literal::new(*l, *k, false))
                     .collect(),
             );
         }
+        clauses
+    }));
+}
+
+/// Au plus `k` des littéraux `lits` sont vrais: compteur séquentiel de Sinz (2005), où la
+/// variable auxiliaire `s[i][j]` est vraie si au moins `j + 1` des littéraux `lits[..=i]` le sont
+fn at_most<W>(out: &mut CNFFile<W>, lits: &[Literal], k: usize) {
+    let n = lits.len();
+    if k >= n {
+        return;
+    }
+    if k == 0 {
+        out.push_multiple(lits.iter().map(|&lit| vec![!lit]));
+        return;
+    }
+
+    let s: Vec<Vec<_>> = (0..n - 1)
+        .map(|_| (0..k).map(|_| out.new_variable()).collect())
+        .collect();
+
+    out.push(vec![!lits[0], s[0][0]]);
+    out.push_multiple(s[0][1..].iter().map(|&aux| vec![!aux]));
+    for i in 1..n - 1 {
+        out.push(vec![!lits[i], s[i][0]]);
+        out.push(vec![!s[i - 1][0], s[i][0]]);
+        for j in 1..k {
+            out.push(vec![!lits[i], !s[i - 1][j - 1], s[i][j]]);
+            out.push(vec![!s[i - 1][j], s[i][j]]);
+        }
+        out.push(vec![!lits[i], !s[i - 1][k - 1]]);
+    }
+    out.push(vec![!lits[n - 1], !s[n - 2][k - 1]]);
+}
+
+/// Règle 1 avec des compteurs séquentiels: au plus n/2 cellules à 1 et au plus n/2 cellules à 0
+/// par ligne et par colonne
+pub fn write_rule_1_sequential<W>(out: &mut CNFFile<W>, grid: &Grid) {
+    let size = grid.size();
+    for k in 0..size {
+        for value in [true, false] {
+            let row: Vec<_> = (0..size).map(|x| Literal::new(x, k, value)).collect();
+            at_most(out, &row, size / 2);
+            let column: Vec<_> = (0..size).map(|y| Literal::new(k, y, value)).collect();
+            at_most(out, &column, size / 2);
+        }
     }
 }
 
 pub fn write_rule_2<W>(out: &mut CNFFile<W>, grid: &Grid) {
-    for x in 0..grid.size {
-        for y in 0..grid.size - 2 {
+    for x in 0..grid.size() {
+        for y in 0..grid.size() - 2 {
             out.push(vec![
                 Literal::new(x, y, true),
                 Literal::new(x, y + 1, true),
@@ -87,8 +143,8 @@ pub fn write_rule_2<W>(out: &mut CNFFile<W>, grid: &Grid) {
             ]);
         }
     }
-    for y in 0..grid.size {
-        for x in 0..grid.size - 2 {
+    for y in 0..grid.size() {
+        for x in 0..grid.size() - 2 {
             out.push(vec![
                 Literal::new(x, y, true),
                 Literal::new(x + 1, y, true),
@@ -106,23 +162,28 @@ pub fn write_rule_2<W>(out: &mut CNFFile<W>, grid: &Grid) {
 /// Renvoie toutes les paires uniques (combinatoire) entre les éléments de `slice`, sous la forme
 /// d'un itérateur (évaluation paresseuse).
 fn pairs<T>(slice: &[T]) -> impl Iterator<Item = (&T, &T)> {
-    std::iter::repeat(slice)
-        .take(slice.len())
+    std::iter::repeat_n(slice, slice.len())
         .enumerate()
         .map(|(idx, slice)| &slice[idx..])
         .filter_map(<[T]>::split_first)
         .flat_map(|(first, seconds)| std::iter::repeat(first).zip(seconds))
 }
 
-pub fn write_rule_3<W>(out: &mut CNFFile<W>, grid: &Grid) {
-    #[derive(Clone, Copy, Eq, PartialEq, Hash)]
+/// Règle 3 par développement de la forme normale disjonctive; l'avancement de la substitution est
+/// envoyé à `progress`.
+///
+/// La substitution est faite en parallèle, par lots d'autant de paires de lignes que de threads;
+/// les clauses de chaque lot sont ajoutées dans l'ordre des paires, et celles de la forme
+/// paramétrique sont triées: la formule ne dépend ni du nombre de threads ni de l'exécution.
+pub fn write_rule_3<W>(out: &mut CNFFile<W>, grid: &Grid, progress: &mut dyn Progress) {
+    #[derive(Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
     enum ParamLiteral {
         A(usize, bool),
         B(usize, bool),
     }
 
     // « Une ligne/colonne A est différente d'une ligne/colonne B » en FND (paramétrique)
-    let diff_a_b_dnf = (0..grid.size)
+    let diff_a_b_dnf = (0..grid.size())
         .flat_map(|z| {
             vec![
                 [ParamLiteral::A(z, true), ParamLiteral::B(z, false)],
@@ -132,67 +193,366 @@ pub fn write_rule_3<W>(out: &mut CNFFile<W>, grid: &Grid) {
         .collect::<Box<[_]>>();
 
     // ... en FNC
-    eprint!("| starting expansion...");
-    let instant_exp = Instant::now();
-    let diff_a_b_cnf = dnf_to_cnf(&diff_a_b_dnf.iter().map(|s| &s[..]).collect::<Vec<_>>()[..]);
-    eprintln!(
-        " DONE ({:?}) ({} clauses)",
-        instant_exp.elapsed(),
-        diff_a_b_cnf.len(),
-    );
-
-    // Liste des nombre de 0 à grid.size
-    let indices = (0..grid.size).collect::<Box<[_]>>();
+    let (diff_a_b_cnf, _) = progress.step("rule 3 expansion", |_| {
+        dnf_to_cnf(&diff_a_b_dnf.iter().map(|s| &s[..]).collect::<Vec<_>>()[..])
+            .into_iter()
+            .map(|clause| {
+                // L'ordre d'un `HashSet` change d'une exécution à l'autre
+                let mut clause: Vec<_> = clause.into_iter().collect();
+                clause.sort_unstable();
+                clause
+            })
+            .collect::<Vec<_>>()
+    });
+
+    // Liste des nombre de 0 à grid.size()
+    let indices = (0..grid.size()).collect::<Box<[_]>>();
 
     // On s'occupe des listes et des colonnes dans la boucle for, puisque les paires sont les mêmes
-    let instant_sub = Instant::now();
-    let pair_count = (indices.len() * (indices.len() - 1)) / 2;
-    for (idx, (a, b)) in pairs(&indices).enumerate() {
-        let (a, b) = (*a, *b);
-
-        eprint!("\r| substituting and writing... {idx}/{pair_count}");
-
-        // Assignation de la forme paramétrique `diff_a_b_cnf` aux lignes
-        let diff_cnf_l = diff_a_b_cnf.iter().map(|clause| {
-            clause
-                .iter()
-                .map(|lit| match *lit {
-                    ParamLiteral::A(x, neg) => Literal::new(x, a, neg),
-                    ParamLiteral::B(x, neg) => Literal::new(x, b, neg),
-                })
-                .collect::<Vec<_>>()
-        });
+    let step = "rule 3 substitution";
+    progress.event(Event::Start { step });
+    let start = Instant::now();
+    let all_pairs: Vec<_> = pairs(&indices).map(|(a, b)| (*a, *b)).collect();
+    let pair_count = all_pairs.len();
 
-        // Assignation de la forme paramétrique `diff_a_b_cnf` aux colonnes
-        let diff_cnf_h = diff_a_b_cnf.iter().map(|clause| {
-            clause
-                .iter()
-                .map(|lit| match *lit {
-                    ParamLiteral::A(y, neg) => Literal::new(a, y, neg),
-                    ParamLiteral::B(y, neg) => Literal::new(b, y, neg),
-                })
-                .collect::<Vec<_>>()
+    // Un lot à la fois: la mémoire des clauses en attente reste bornée
+    for (batch, chunk) in all_pairs.chunks(rayon::current_num_threads()).enumerate() {
+        progress.event(Event::Advance {
+            step,
+            done: batch * rayon::current_num_threads(),
+            total: pair_count,
         });
 
-        out.push_multiple(diff_cnf_l.chain(diff_cnf_h));
+        out.push_batches(chunk.par_iter().map(|&(a, b)| {
+            // Assignation de la forme paramétrique `diff_a_b_cnf` aux lignes
+            let diff_cnf_l = diff_a_b_cnf.iter().map(move |clause| {
+                clause
+                    .iter()
+                    .map(|lit| match *lit {
+                        ParamLiteral::A(x, neg) => Literal::new(x, a, neg),
+                        ParamLiteral::B(x, neg) => Literal::new(x, b, neg),
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            // Assignation de la forme paramétrique `diff_a_b_cnf` aux colonnes
+            let diff_cnf_h = diff_a_b_cnf.iter().map(move |clause| {
+                clause
+                    .iter()
+                    .map(|lit| match *lit {
+                        ParamLiteral::A(y, neg) => Literal::new(a, y, neg),
+                        ParamLiteral::B(y, neg) => Literal::new(b, y, neg),
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            diff_cnf_l.chain(diff_cnf_h)
+        }));
     }
-    eprintln!(
-        "\r| substituting and writing... DONE ({:?})",
-        instant_sub.elapsed()
-    );
+    progress.event(Event::Advance {
+        step,
+        done: pair_count,
+        total: pair_count,
+    });
+    progress.event(Event::Finish {
+        step,
+        duration: start.elapsed(),
+    });
 }
 
-pub fn write_all<W>(out: &mut CNFFile<W>, grid: &Grid) {
-    let mut run_rule = |rule: fn(&mut CNFFile<W>, &Grid), no: u8| {
-        eprintln!("[rule {no}] starting rule");
-        let start = Instant::now();
-        rule(out, grid);
-        eprintln!("\\ DONE ({:?})", start.elapsed());
+/// Règle 3 avec des variables auxiliaires (transformation de Tseitin): pour chaque paire de
+/// lignes, une variable par position qui n'est vraie que si les deux lignes y diffèrent, et une
+/// clause qui exige qu'une de ces variables soit vraie
+pub fn write_rule_3_tseitin<W>(out: &mut CNFFile<W>, grid: &Grid) {
+    let size = grid.size();
+    let indices = (0..size).collect::<Box<[_]>>();
+
+    let mut distinct = |a: &dyn Fn(usize) -> Literal, b: &dyn Fn(usize) -> Literal| {
+        let differences: Vec<_> = (0..size)
+            .map(|z| {
+                let difference = out.new_variable();
+                out.push(vec![!difference, a(z), b(z)]);
+                out.push(vec![!difference, !a(z), !b(z)]);
+                difference
+            })
+            .collect();
+        out.push(differences);
     };
 
-    run_rule(write_rule_1, 1);
-    run_rule(write_rule_2, 2);
-    run_rule(write_rule_3, 3);
+    for (&a, &b) in pairs(&indices) {
+        distinct(&|x| Literal::new(x, a, true), &|x| Literal::new(x, b, true));
+        distinct(&|y| Literal::new(a, y, true), &|y| Literal::new(b, y, true));
+    }
+}
+
+/// Ensemble des règles imposées à une grille
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RuleSet {
+    /// Règle 1: autant de 0 que de 1 sur chaque ligne et chaque colonne
+    pub balance: bool,
+
+    /// Règle 2: jamais trois cellules identiques consécutives
+    pub no_triples: bool,
+
+    /// Règle 3: lignes deux à deux distinctes, colonnes deux à deux distinctes
+    pub unique_lines: bool,
+}
+
+impl RuleSet {
+    /// Règles du Takuzu: les trois règles s'appliquent
+    pub const TAKUZU: RuleSet = RuleSet {
+        balance: true,
+        no_triples: true,
+        unique_lines: true,
+    };
+
+    /// Règles par défaut d'Unruly (collection de Simon Tatham), où l'unicité des lignes est une
+    /// option
+    pub const UNRULY: RuleSet = RuleSet {
+        unique_lines: false,
+        ..RuleSet::TAKUZU
+    };
+
+    /// Nom reconnu par `FromStr`: `takuzu`, `unruly`, ou la liste des numéros des règles (vide si
+    /// aucune règle ne s'applique)
+    pub fn name(self) -> &'static str {
+        match (self.balance, self.no_triples, self.unique_lines) {
+            (true, true, true) => "takuzu",
+            (true, true, false) => "unruly",
+            (true, false, true) => "1,3",
+            (false, true, true) => "2,3",
+            (true, false, false) => "1",
+            (false, true, false) => "2",
+            (false, false, true) => "3",
+            (false, false, false) => "",
+        }
+    }
+}
+
+/// `takuzu`, `unruly`, ou liste des numéros des règles, par exemple `1,2`
+impl FromStr for RuleSet {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "takuzu" => return Ok(RuleSet::TAKUZU),
+            "unruly" => return Ok(RuleSet::UNRULY),
+            _ => {}
+        }
+
+        let mut rules = RuleSet {
+            balance: false,
+            no_triples: false,
+            unique_lines: false,
+        };
+        for rule in s.split(',') {
+            match rule.trim() {
+                "1" => rules.balance = true,
+                "2" => rules.no_triples = true,
+                "3" => rules.unique_lines = true,
+                _ => return Err(()),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+/// Taille de la partie de la formule produite par une règle
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RuleStats {
+    pub rule: u8,
+    pub clauses: usize,
+    pub literals: usize,
+
+    /// Nombre de clauses par longueur
+    pub lengths: BTreeMap<usize, usize>,
+
+    /// Clauses écartées car satisfaites par une cellule donnée
+    pub removed: usize,
+
+    pub aux_variables: usize,
+
+    /// Taille de ces clauses au format DIMACS, en octets
+    pub dimacs_bytes: usize,
+
+    #[serde(rename = "ms", serialize_with = "progress::milliseconds")]
+    pub duration: Duration,
+}
+
+pub fn write_all<W>(out: &mut CNFFile<W>, grid: &Grid) -> Vec<RuleStats> {
+    write_rules(out, grid, &EncodingOptions::default(), &mut Silent)
+}
+
+/// Écrit les clauses des règles choisies dans `options` uniquement, avec les encodages choisis, et
+/// renvoie la taille de chacune. Chaque règle est une étape `rule <numéro>` de `progress`.
+pub fn write_rules<W>(
+    out: &mut CNFFile<W>,
+    grid: &Grid,
+    options: &EncodingOptions,
+    progress: &mut dyn Progress,
+) -> Vec<RuleStats> {
+    let mut stats = Vec::new();
+
+    let mut run_rule = |no: u8, rule: &mut dyn FnMut(&mut CNFFile<W>, &mut dyn Progress)| {
+        let (clauses, literals) = (out.len(), out.literal_count());
+        let (removed, aux_variables) = (out.removed_count(), out.aux_count());
+        let ((), duration) = progress.step(&format!("rule {no}"), |progress| rule(out, progress));
+
+        let mut lengths = BTreeMap::new();
+        let mut dimacs_bytes = 0;
+        for clause in out.clauses_from(clauses) {
+            *lengths.entry(clause.len()).or_insert(0) += 1;
+            dimacs_bytes += out.dimacs_size(clause);
+        }
+
+        stats.push(RuleStats {
+            rule: no,
+            clauses: out.len() - clauses,
+            literals: out.literal_count() - literals,
+            lengths,
+            removed: out.removed_count() - removed,
+            aux_variables: out.aux_count() - aux_variables,
+            dimacs_bytes,
+            duration,
+        });
+    };
+
+    let rules = options.rules;
+    if rules.balance {
+        match options.balance {
+            BalanceEncoding::Combinations => run_rule(1, &mut |out, _| write_rule_1(out, grid)),
+            BalanceEncoding::Sequential => {
+                run_rule(1, &mut |out, _| write_rule_1_sequential(out, grid))
+            }
+        }
+    }
+    if rules.no_triples {
+        run_rule(2, &mut |out, _| write_rule_2(out, grid));
+    }
+    if rules.unique_lines {
+        match options.uniqueness {
+            UniquenessEncoding::Expansion => {
+                run_rule(3, &mut |out, progress| write_rule_3(out, grid, progress))
+            }
+            UniquenessEncoding::Tseitin => {
+                run_rule(3, &mut |out, _| write_rule_3_tseitin(out, grid))
+            }
+        }
+    }
+
+    stats
+}
+
+/// Encodage de la règle 1
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BalanceEncoding {
+    /// Toute combinaison de n/2 + 1 cellules d'une ligne contient un 0 et un 1: aucune variable
+    /// auxiliaire, mais un nombre de clauses exponentiel en n
+    #[default]
+    Combinations,
+
+    /// Compteurs séquentiels: O(n²) clauses et variables auxiliaires par ligne
+    Sequential,
+}
+
+impl BalanceEncoding {
+    pub const ALL: [BalanceEncoding; 2] =
+        [BalanceEncoding::Combinations, BalanceEncoding::Sequential];
+
+    /// Nom reconnu par `FromStr`
+    pub fn name(self) -> &'static str {
+        match self {
+            BalanceEncoding::Combinations => "combinations",
+            BalanceEncoding::Sequential => "sequential",
+        }
+    }
+}
+
+impl FromStr for BalanceEncoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "combinations" => Ok(BalanceEncoding::Combinations),
+            "sequential" => Ok(BalanceEncoding::Sequential),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Encodage de la règle 3
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum UniquenessEncoding {
+    /// Développement en FNC de « les lignes A et B diffèrent »: 4^n clauses par paire de lignes,
+    /// sans variable auxiliaire
+    #[default]
+    Expansion,
+
+    /// Transformation de Tseitin: 2n + 1 clauses et n variables auxiliaires par paire de lignes
+    Tseitin,
+}
+
+impl UniquenessEncoding {
+    pub const ALL: [UniquenessEncoding; 2] =
+        [UniquenessEncoding::Expansion, UniquenessEncoding::Tseitin];
+
+    /// Nom reconnu par `FromStr`
+    pub fn name(self) -> &'static str {
+        match self {
+            UniquenessEncoding::Expansion => "expansion",
+            UniquenessEncoding::Tseitin => "tseitin",
+        }
+    }
+}
+
+impl FromStr for UniquenessEncoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "expansion" => Ok(UniquenessEncoding::Expansion),
+            "tseitin" => Ok(UniquenessEncoding::Tseitin),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Options de l'encodage d'une grille en CNF
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EncodingOptions {
+    /// Règles encodées
+    pub rules: RuleSet,
+
+    pub balance: BalanceEncoding,
+    pub uniqueness: UniquenessEncoding,
+}
+
+impl Default for EncodingOptions {
+    fn default() -> Self {
+        RuleSet::TAKUZU.into()
+    }
+}
+
+impl From<RuleSet> for EncodingOptions {
+    fn from(rules: RuleSet) -> Self {
+        EncodingOptions {
+            rules,
+            balance: BalanceEncoding::default(),
+            uniqueness: UniquenessEncoding::default(),
+        }
+    }
+}
+
+/// Encode la grille (cellules données comprises) en une formule `varisat`, et renvoie la taille
+/// de chaque règle
+pub fn encode(
+    grid: &Grid,
+    options: &EncodingOptions,
+    progress: &mut dyn Progress,
+) -> (CnfFormula, Vec<RuleStats>) {
+    let mut cnf = CNFFile::new_varisat(grid);
+    let stats = write_rules(&mut cnf, grid, options, progress);
+    (cnf.into_varisat(), stats)
 }
 
 #[cfg(test)]
@@ -211,7 +571,7 @@ mod tests {
 
         assert_eq!(EXPECTED.len(), pairs.len(), "{:?}", pairs);
         for expected_pair in EXPECTED {
-            let expected_pair = &HashSet::<_>::from_iter(expected_pair.into_iter().copied());
+            let expected_pair = &HashSet::<_>::from_iter(expected_pair.iter().copied());
             assert!(
                 pairs.contains(expected_pair),
                 "{:?} ∉ {:?}",
@@ -221,6 +581,106 @@ mod tests {
         }
     }
 
+    #[test]
+    fn stats() {
+        let grid = Grid::new(4);
+        let mut out = CNFFile::new_varisat(&grid);
+
+        let stats = write_all(&mut out, &grid);
+
+        let sizes: Vec<_> = stats
+            .iter()
+            .map(|s| (s.rule, s.clauses, s.literals))
+            .collect();
+        // Règle 3: 2^(2n) clauses de 2n littéraux par paire de lignes et par paire de colonnes
+        assert_eq!(
+            sizes,
+            vec![(1, 64, 192), (2, 32, 96), (3, 12 * 256, 12 * 256 * 8)],
+        );
+        assert_eq!(out.len(), 64 + 32 + 12 * 256);
+    }
+
+    #[test]
+    fn rule_set_names() {
+        for bits in 1..8 {
+            let rules = RuleSet {
+                balance: bits & 1 != 0,
+                no_triples: bits & 2 != 0,
+                unique_lines: bits & 4 != 0,
+            };
+            assert_eq!(rules.name().parse(), Ok(rules), "{}", rules.name());
+        }
+        assert_eq!("1,2".parse::<RuleSet>().unwrap().name(), "unruly");
+    }
+
+    #[test]
+    fn encode_options() {
+        let (formula, stats) = encode(&Grid::new(4), &EncodingOptions::default(), &mut Silent);
+        assert_eq!(formula.len(), 64 + 32 + 12 * 256);
+        assert_eq!(stats.len(), 3);
+
+        let (formula, stats) = encode(&Grid::new(4), &RuleSet::UNRULY.into(), &mut Silent);
+        assert_eq!(formula.len(), 64 + 32);
+        assert_eq!(stats.len(), 2);
+    }
+
+    #[test]
+    fn progress_events() {
+        #[derive(Default)]
+        struct Steps(Vec<String>, usize);
+
+        impl Progress for Steps {
+            fn event(&mut self, event: Event<'_>) {
+                match event {
+                    Event::Start { step } => self.0.push(step.to_owned()),
+                    Event::Advance { done, total, .. } => {
+                        assert!(done <= total);
+                        self.1 += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut steps = Steps::default();
+        encode(&Grid::new(4), &EncodingOptions::default(), &mut steps);
+        assert_eq!(
+            steps.0,
+            [
+                "rule 1",
+                "rule 2",
+                "rule 3",
+                "rule 3 expansion",
+                "rule 3 substitution"
+            ]
+        );
+        // Une étape par lot de paires de lignes (6 paires), puis la fin
+        let batches = 6usize.div_ceil(rayon::current_num_threads());
+        assert_eq!(steps.1, batches + 1);
+    }
+
+    /// La formule est la même quel que soit le nombre de threads
+    #[test]
+    fn deterministic() {
+        let grid =
+            crate::grid_read::parse("6\n1....0\n..0...\n......\n.1..1.\n......\n0....1\n").unwrap();
+        let dimacs = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                let mut cnf = CNFFile::new(&grid, Vec::new());
+                write_all(&mut cnf, &grid);
+                cnf.save().unwrap()
+            })
+        };
+
+        let expected = dimacs(1);
+        assert_eq!(dimacs(3), expected);
+        assert_eq!(dimacs(8), expected);
+    }
+
     #[test]
     fn combinations_test() {
         // eprintln!("{:#?}", combinations(4, 8));
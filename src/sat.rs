@@ -1,4 +1,7 @@
+//! Lecture des fichiers de résultats des SAT-solveurs
 use super::*;
+use crate::progress::{Event, Progress};
+use std::collections::hash_map::{Entry, HashMap};
 use std::io::BufRead;
 
 #[derive(Debug, Eq, PartialEq)]
@@ -15,82 +18,220 @@ pub enum Error {
     /// Le format du littéral n'est pas reconnu
     InvalidLiteral(String),
 
-    /// Le nombre de variables du modèle n'a pas de racine entière: impossible de déterminer la
-    /// taille de la grille
+    /// La taille de la grille ne peut pas être déduite du modèle (aucune variable, ou une grille
+    /// de plus de 64x64), ou la taille donnée est impaire
     InvalidModel,
+
+    /// Le fichier se termine avant le `0` qui clôt le modèle
+    UnterminatedModel,
+
+    /// Le modèle affecte les deux valeurs à cette variable
+    ContradictoryLiteral(usize),
+}
+
+/// Littéraux d'un modèle, accumulés sur une ou plusieurs lignes
+#[derive(Default)]
+struct Model {
+    /// Valeur de chaque variable (numérotée à partir de 1) présente dans le modèle
+    values: HashMap<usize, bool>,
+
+    /// Le `0` final a été lu
+    complete: bool,
+
+    /// Nombre de variables lues parmi les `cells` premières (les cellules de la grille)
+    read_cells: usize,
+    cells: usize,
 }
 
-fn create_grid(model: &str) -> Result<Grid, Error> {
-    let model = model
-        .strip_suffix(" 0")
-        .ok_or(Error::InvalidLine(model.into()))?;
-
-    let cells: Vec<_> = model
-        .split_ascii_whitespace()
-        .enumerate()
-        .map(|(idx, lit)| {
-            let (atom, is_one) = if let Some(atom) = lit.strip_prefix('-') {
-                (atom, false)
-            } else {
-                (lit, true)
+impl Model {
+    /// Ajoute les littéraux d'une ligne, jusqu'au `0` final éventuel
+    fn push_line(&mut self, line: &str) -> Result<(), Error> {
+        for lit in line.split_ascii_whitespace() {
+            let (atom, is_one) = match lit.strip_prefix('-') {
+                Some(atom) => (atom, false),
+                None => (lit, true),
             };
+            let var: usize = atom
+                .parse()
+                .map_err(|_| Error::InvalidLiteral(lit.into()))?;
 
-            if (idx + 1).to_string() == atom {
-                Ok(Cell::Filled(is_one))
-            } else {
-                Err(Error::InvalidLiteral(lit.into()))
+            if var == 0 {
+                self.complete = true;
+                return Ok(());
             }
-        })
-        .collect::<Result<_, _>>()?;
 
-    Grid::try_from(cells).map_err(|()| Error::InvalidModel)
+            match self.values.entry(var) {
+                Entry::Occupied(entry) if *entry.get() != is_one => {
+                    return Err(Error::ContradictoryLiteral(var))
+                }
+                Entry::Occupied(_) => {}
+                Entry::Vacant(entry) => {
+                    entry.insert(is_one);
+                    if var <= self.cells {
+                        self.read_cells += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Taille déduite d'un modèle dont la plus grande variable est `max`: la plus petite taille paire
+/// qui contient toutes les variables, au plus [`bitboard::MAX_SIZE`] (au-delà, une seule variable
+/// suffirait à allouer une grille démesurée)
+fn inferred_size(max: usize) -> Option<usize> {
+    let root = max.isqrt();
+    let size = if root * root < max { root + 1 } else { root };
+    let size = size + size % 2;
+    Some(size).filter(|&size| size <= bitboard::MAX_SIZE)
+}
+
+/// Construit la grille; les variables absentes du modèle donnent des cellules vides. Sans `size`,
+/// la taille est déduite du modèle (voir [`inferred_size`]); sinon les variables au-delà de la
+/// grille (variables auxiliaires) sont ignorées.
+fn values_to_grid(values: HashMap<usize, bool>, size: Option<usize>) -> Result<Grid, Error> {
+    let size = match size {
+        Some(size) => size,
+        None => {
+            inferred_size(values.keys().copied().max().unwrap_or(0)).ok_or(Error::InvalidModel)?
+        }
+    };
+    if size == 0 || size % 2 != 0 {
+        return Err(Error::InvalidModel);
+    }
+
+    let mut grid = Grid::new(size);
+    for (var, value) in values {
+        let index = var - 1;
+        if index < size * size {
+            grid.set(index % size, index / size, value);
+        }
+    }
+
+    Ok(grid)
 }
 
 /// Lis un fichier de résultats du SAT-Solveur et retourne la grille correspondante. Supporte les
 /// formats:
 ///   - MiniSAT
 ///   - Varisat
-///   - <http://www.satcompetition.org/2004/format-solvers2004.html>
-pub fn read_sat_file(reader: impl BufRead) -> Result<Grid, Error> {
+///   - <http://www.satcompetition.org/2004/format-solvers2004.html>, y compris les modèles répartis
+///     sur plusieurs lignes `v`
+///
+/// `size` est la taille de la grille si elle est connue; sinon elle est déduite du modèle. La
+/// lecture est l'étape `reading model` de `progress`, qui avance à chaque ligne du modèle si la
+/// taille est connue.
+pub fn read_sat_file(
+    reader: impl BufRead,
+    size: Option<usize>,
+    progress: &mut dyn Progress,
+) -> Result<Grid, Error> {
+    let cells = size.map(|size| size * size);
+    values_to_grid(read_values(reader, cells, progress)?, size)
+}
+
+/// Lis un fichier de résultats comme [`read_sat_file`], et renvoie la valeur de chaque variable
+/// du modèle (numérotées à partir de 1)
+pub fn read_model(
+    reader: impl BufRead,
+    progress: &mut dyn Progress,
+) -> Result<HashMap<usize, bool>, Error> {
+    read_values(reader, None, progress)
+}
+
+/// Lis le modèle comme l'étape `reading model`; l'avancement est le nombre de variables lues
+/// parmi les `cells` premières, si ce nombre est connu
+fn read_values(
+    reader: impl BufRead,
+    cells: Option<usize>,
+    progress: &mut dyn Progress,
+) -> Result<HashMap<usize, bool>, Error> {
+    progress
+        .step("reading model", |progress| {
+            read_lines(reader, cells, progress)
+        })
+        .0
+}
+
+fn read_lines(
+    reader: impl BufRead,
+    cells: Option<usize>,
+    progress: &mut dyn Progress,
+) -> Result<HashMap<usize, bool>, Error> {
+    let new_model = || Model {
+        cells: cells.unwrap_or(0),
+        ..Model::default()
+    };
+    let mut model: Option<Model> = None;
+
     for line in reader.lines() {
         let line = line.unwrap();
 
         match line.trim() {
             "SAT" | "s SATISFIABLE" | "" => continue,
-            line if line.starts_with("c ") => continue,
+            line if line.starts_with("c ") || line == "c" => continue,
 
             "UNSAT" | "s UNSATISFIABLE" => return Err(Error::Unsatisfiable),
 
-            model
-                if model
-                    .chars()
-                    .next()
-                    .filter(|c| *c == '-' || c.is_numeric())
-                    .is_some() =>
-            {
-                return create_grid(model)
+            line if line == "v" || line.starts_with("v ") => {
+                model.get_or_insert_with(new_model).push_line(&line[1..])?
             }
 
-            // line if let Some(model) = line.strip_suffix("v ") => return create_grid(model), [PAS ENCORE STABLE]
-            line if line.starts_with("v ") => return create_grid(&line[2..]),
+            // MiniSAT écrit le modèle sans préfixe
+            line if line
+                .chars()
+                .next()
+                .filter(|c| *c == '-' || c.is_numeric())
+                .is_some() =>
+            {
+                model.get_or_insert_with(new_model).push_line(line)?
+            }
 
             line => return Err(Error::InvalidLine(line.into())),
         }
+
+        if let (Some(total), Some(model)) = (cells, &model) {
+            progress.event(Event::Advance {
+                step: "reading model",
+                done: model.read_cells,
+                total,
+            });
+        }
+        if model.as_ref().is_some_and(|model| model.complete) {
+            return Ok(model.unwrap().values);
+        }
     }
 
-    Err(Error::UndefinedModel)
+    match model {
+        Some(_) => Err(Error::UnterminatedModel),
+        None => Err(Error::UndefinedModel),
+    }
+}
+
+/// Taille de la grille indiquée par le commentaire `c takuzu size <n>` qu'écrit
+/// [`crate::cnf::CNFFile::save`] avant l'en-tête d'un fichier DIMACS, s'il est présent. Seuls les
+/// commentaires qui précèdent l'en-tête sont lus.
+pub fn formula_size(reader: impl BufRead) -> Option<usize> {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .take_while(|line| !line.starts_with('p'))
+        .find_map(|line| line.strip_prefix("c takuzu size ")?.trim().parse().ok())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::progress::Silent;
 
     #[test]
     fn unsat() {
         const FILE: &str = "c File created by MyIncredibleSAT\n\ns UNSATISFIABLE\n";
 
         assert_eq!(
-            read_sat_file(FILE.as_bytes()).unwrap_err(),
+            read_sat_file(FILE.as_bytes(), None, &mut Silent).unwrap_err(),
             Error::Unsatisfiable,
         );
     }
@@ -100,7 +241,7 @@ mod tests {
         const FILE: &str = "s SATISFIABLE\n";
 
         assert_eq!(
-            read_sat_file(FILE.as_bytes()).unwrap_err(),
+            read_sat_file(FILE.as_bytes(), None, &mut Silent).unwrap_err(),
             Error::UndefinedModel,
         );
     }
@@ -109,10 +250,10 @@ mod tests {
     fn sat_minisat() {
         const FILE: &str = "SAT\n-1 2 -3 4 0\n";
 
-        let grid = read_sat_file(FILE.as_bytes()).unwrap();
+        let grid = read_sat_file(FILE.as_bytes(), None, &mut Silent).unwrap();
 
         assert_eq!(
-            grid.inner,
+            grid.inner(),
             vec![
                 Cell::Filled(false),
                 Cell::Filled(true),
@@ -126,10 +267,10 @@ mod tests {
     fn sat_varisat() {
         const FILE: &str = "s SATISFIABLE\nv -1 2 -3 4 0\n";
 
-        let grid = read_sat_file(FILE.as_bytes()).unwrap();
+        let grid = read_sat_file(FILE.as_bytes(), None, &mut Silent).unwrap();
 
         assert_eq!(
-            grid.inner,
+            grid.inner(),
             vec![
                 Cell::Filled(false),
                 Cell::Filled(true),
@@ -138,4 +279,80 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn multiline_unordered() {
+        const FILE: &str = "s SATISFIABLE\nv 3 -1\nv 2\nv -4 0\n";
+
+        let grid = read_sat_file(FILE.as_bytes(), None, &mut Silent).unwrap();
+
+        assert_eq!(
+            grid.inner(),
+            vec![
+                Cell::Filled(false),
+                Cell::Filled(true),
+                Cell::Filled(true),
+                Cell::Filled(false),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_variables() {
+        let grid = read_sat_file("v 1 -3 0\n".as_bytes(), None, &mut Silent).unwrap();
+        assert_eq!(
+            grid.inner(),
+            vec![
+                Cell::Filled(true),
+                Cell::Empty,
+                Cell::Filled(false),
+                Cell::Empty,
+            ]
+        );
+
+        // La taille déduite du modèle peut être trop petite, d'où la taille explicite
+        let grid = read_sat_file("v 1 -3 0\n".as_bytes(), Some(4), &mut Silent).unwrap();
+        assert_eq!(grid.size(), 4);
+
+        // La taille déduite est arrondie à la taille paire suivante
+        let grid = read_sat_file("v 1 -5 0\n".as_bytes(), None, &mut Silent).unwrap();
+        assert_eq!(grid.size(), 4);
+        assert_eq!(grid.get(0, 1), Cell::Filled(false));
+
+        // Au-delà de 64x64, la taille doit être donnée
+        assert_eq!(
+            read_sat_file("v 4000000000 0\n".as_bytes(), None, &mut Silent).unwrap_err(),
+            Error::InvalidModel
+        );
+        let grid = read_sat_file("v -4096 0\n".as_bytes(), None, &mut Silent).unwrap();
+        assert_eq!(grid.size(), 64);
+
+        // Variables auxiliaires au-delà de la grille
+        let grid = read_sat_file("v 1 2 3 4 -5 6 0\n".as_bytes(), Some(2), &mut Silent).unwrap();
+        assert_eq!(grid.inner(), vec![Cell::Filled(true); 4]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            read_sat_file("v 1 2 -1 0\n".as_bytes(), None, &mut Silent).unwrap_err(),
+            Error::ContradictoryLiteral(1),
+        );
+        assert_eq!(
+            read_sat_file("v 1 -2\nv 3\n".as_bytes(), None, &mut Silent).unwrap_err(),
+            Error::UnterminatedModel,
+        );
+        assert_eq!(
+            read_sat_file("v 1 x2 0\n".as_bytes(), None, &mut Silent).unwrap_err(),
+            Error::InvalidLiteral("x2".into()),
+        );
+        assert_eq!(
+            read_sat_file("v 0\n".as_bytes(), None, &mut Silent).unwrap_err(),
+            Error::InvalidModel,
+        );
+        assert_eq!(
+            read_sat_file("v 1 0\n".as_bytes(), Some(3), &mut Silent).unwrap_err(),
+            Error::InvalidModel,
+        );
+    }
 }
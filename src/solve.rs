@@ -0,0 +1,227 @@
+//! Résolution d'une grille avec un [`SatBackend`] (`varisat` par défaut), sans passer par un
+//! fichier DIMACS
+use crate::backend::{self, SatBackend, VarisatBackend};
+use crate::progress::{Progress, Silent};
+use crate::rules::{self, EncodingOptions, RuleSet};
+use crate::validate;
+use crate::{Cell, Grid};
+use serde::Serialize;
+use std::time::Duration;
+use varisat::Lit;
+
+/// Issue de la résolution d'une grille
+#[derive(Clone, Debug)]
+pub enum Outcome {
+    /// La grille admet exactement une solution
+    Solved(Grid),
+
+    /// La grille n'admet aucune solution
+    Unsat,
+
+    /// La grille admet plusieurs solutions, dont ces deux-là
+    Ambiguous(Grid, Grid),
+}
+
+/// Issue d'une résolution, telle qu'indiquée dans les rapports
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Solved,
+    Unsat,
+    Ambiguous,
+    Error,
+}
+
+impl Status {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Solved => "solved",
+            Status::Unsat => "unsat",
+            Status::Ambiguous => "ambiguous",
+            Status::Error => "error",
+        }
+    }
+}
+
+impl From<&Outcome> for Status {
+    fn from(outcome: &Outcome) -> Self {
+        match outcome {
+            Outcome::Solved(_) => Status::Solved,
+            Outcome::Unsat => Status::Unsat,
+            Outcome::Ambiguous(..) => Status::Ambiguous,
+        }
+    }
+}
+
+/// Durées des différentes étapes de la résolution
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timings {
+    pub encoding: Duration,
+    pub solving: Duration,
+}
+
+/// Reconstruit une grille à partir d'un modèle `varisat`, les variables étant numérotées comme
+/// dans [`crate::cnf::CNFFile`]
+pub fn model_to_grid(size: usize, model: &[Lit]) -> Grid {
+    let mut grid = Grid::new(size);
+
+    for lit in model {
+        let index = lit.var().index();
+        if index < size * size {
+            grid.set(index % size, index / size, lit.is_positive());
+        }
+    }
+
+    grid
+}
+
+/// Clause interdisant exactement la grille (complète) `grid`
+fn blocking_clause(grid: &Grid) -> Vec<Lit> {
+    grid.inner()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, cell)| match *cell {
+            Cell::Filled(value) => Some(Lit::from_index(index, !value)),
+            Cell::Empty => None,
+        })
+        .collect()
+}
+
+/// Résoud la grille avec les règles du Takuzu et vérifie l'unicité de la solution
+pub fn solve(grid: &Grid) -> Result<(Outcome, Timings), backend::Error> {
+    solve_with(grid, RuleSet::TAKUZU)
+}
+
+/// Comme [`solve`], avec un autre ensemble de règles
+pub fn solve_with(grid: &Grid, rules: RuleSet) -> Result<(Outcome, Timings), backend::Error> {
+    solve_with_backend(
+        grid,
+        &rules.into(),
+        &mut VarisatBackend::default(),
+        &mut Silent,
+    )
+}
+
+/// Comme [`solve_with`], avec les options d'encodage `options` et le solveur `solver`, qui doit
+/// être vide. L'encodage et la résolution sont les étapes `encoding` et `solving` de `progress`.
+pub fn solve_with_backend(
+    grid: &Grid,
+    options: &EncodingOptions,
+    solver: &mut dyn SatBackend,
+    progress: &mut dyn Progress,
+) -> Result<(Outcome, Timings), backend::Error> {
+    let mut timings = Timings::default();
+
+    let ((formula, _), duration) = progress.step("encoding", |progress| {
+        rules::encode(grid, options, progress)
+    });
+    timings.encoding = duration;
+
+    solver.add_formula(&formula);
+
+    let (outcome, duration) = progress.step("solving", |_| find_solutions(grid, options, solver));
+    timings.solving = duration;
+
+    Ok((outcome?, timings))
+}
+
+/// Cherche une solution, puis une seconde pour vérifier l'unicité. `solver` doit déjà contenir
+/// la formule de `grid` encodée avec `options`.
+pub fn find_solutions(
+    grid: &Grid,
+    options: &EncodingOptions,
+    solver: &mut dyn SatBackend,
+) -> Result<Outcome, backend::Error> {
+    Ok(if solver.solve()? {
+        let first = model_to_grid(grid.size(), &solver.model().unwrap());
+        debug_assert!(validate::is_solution(&first, options.rules));
+
+        solver.add_clause(&blocking_clause(&first));
+        if solver.solve()? {
+            let second = model_to_grid(grid.size(), &solver.model().unwrap());
+            Outcome::Ambiguous(first, second)
+        } else {
+            Outcome::Solved(first)
+        }
+    } else {
+        Outcome::Unsat
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_read;
+
+    #[test]
+    fn solved() {
+        let grid = grid_read::parse("4\n.1.0\n..0.\n.0..\n11.0\n").unwrap();
+
+        let (outcome, _) = solve(&grid).unwrap();
+
+        match outcome {
+            Outcome::Solved(solution) => {
+                assert_eq!(grid_read::format(&solution), "4\n0110\n1001\n0011\n1100\n")
+            }
+            outcome => panic!("{:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn ambiguous() {
+        let (outcome, _) = solve(&Grid::new(4)).unwrap();
+
+        assert!(matches!(outcome, Outcome::Ambiguous(..)));
+    }
+
+    #[test]
+    fn unsat() {
+        let grid = grid_read::parse("4\n000.\n....\n....\n....\n").unwrap();
+
+        let (outcome, _) = solve(&grid).unwrap();
+
+        assert!(matches!(outcome, Outcome::Unsat));
+    }
+
+    #[test]
+    fn encodings() {
+        use crate::rules::{BalanceEncoding, UniquenessEncoding};
+
+        let grid = grid_read::parse("4\n.1.0\n..0.\n.0..\n11.0\n").unwrap();
+        for balance in [BalanceEncoding::Combinations, BalanceEncoding::Sequential] {
+            for uniqueness in [UniquenessEncoding::Expansion, UniquenessEncoding::Tseitin] {
+                let options = EncodingOptions {
+                    balance,
+                    uniqueness,
+                    ..EncodingOptions::default()
+                };
+                let (outcome, _) = solve_with_backend(
+                    &grid,
+                    &options,
+                    &mut VarisatBackend::default(),
+                    &mut Silent,
+                )
+                .unwrap();
+
+                match outcome {
+                    Outcome::Solved(solution) => {
+                        assert_eq!(grid_read::format(&solution), "4\n0110\n1001\n0011\n1100\n")
+                    }
+                    outcome => panic!("{:?} {:?}: {:?}", balance, uniqueness, outcome),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rule_sets() {
+        // La seule complétion répète la deuxième ligne
+        let grid = grid_read::parse("4\n0101\n1010\n0101\n....\n").unwrap();
+
+        let (outcome, _) = solve(&grid).unwrap();
+        assert!(matches!(outcome, Outcome::Unsat));
+
+        let (outcome, _) = solve_with(&grid, RuleSet::UNRULY).unwrap();
+        assert!(matches!(outcome, Outcome::Solved(_)));
+    }
+}
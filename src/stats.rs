@@ -0,0 +1,154 @@
+//! Statistiques de l'encodage d'une grille: taille de la formule, règle par règle
+use crate::cnf::CNFFile;
+use crate::progress::Progress;
+use crate::rules::{self, EncodingOptions, RuleStats};
+use crate::Grid;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// Taille de la formule complète d'une grille, telle qu'écrite par le mode `stats`
+#[derive(Debug, Serialize)]
+pub struct FormulaStats {
+    pub size: usize,
+
+    /// Cellules données, une clause unitaire chacune
+    pub givens: usize,
+
+    pub variables: usize,
+    pub aux_variables: usize,
+    pub clauses: usize,
+    pub literals: usize,
+
+    /// Clauses écartées car satisfaites par une cellule donnée
+    pub removed: usize,
+
+    /// Taille du fichier DIMACS, en-tête compris, en octets
+    pub dimacs_bytes: usize,
+
+    pub rules: Vec<RuleStats>,
+}
+
+/// Encode la grille avec `options` et mesure la formule obtenue
+pub fn analyse(
+    grid: &Grid,
+    options: &EncodingOptions,
+    progress: &mut dyn Progress,
+) -> FormulaStats {
+    let mut cnf = CNFFile::new_varisat(grid);
+    let givens = cnf.len();
+    let rules = rules::write_rules(&mut cnf, grid, options, progress);
+
+    FormulaStats {
+        size: grid.size(),
+        givens,
+        variables: cnf.variable_count(),
+        aux_variables: cnf.aux_count(),
+        clauses: cnf.len(),
+        literals: cnf.literal_count(),
+        removed: cnf.removed_count(),
+        dimacs_bytes: cnf.dimacs_len(),
+        rules,
+    }
+}
+
+impl FormulaStats {
+    pub fn write_text(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(
+            w,
+            "grille {0}x{0}, {1} cellules données",
+            self.size, self.givens
+        )?;
+        writeln!(
+            w,
+            "variables: {} (dont {} auxiliaires)",
+            self.variables, self.aux_variables
+        )?;
+        writeln!(
+            w,
+            "clauses: {} ({} écartées), littéraux: {}, DIMACS: {} octets",
+            self.clauses, self.removed, self.literals, self.dimacs_bytes
+        )?;
+
+        for rule in &self.rules {
+            writeln!(w)?;
+            writeln!(
+                w,
+                "règle {}: {} clauses ({} écartées), {} littéraux, {} variables auxiliaires",
+                rule.rule, rule.clauses, rule.removed, rule.literals, rule.aux_variables
+            )?;
+            writeln!(
+                w,
+                "  {:.2} ms, DIMACS: {} octets",
+                rule.duration.as_secs_f64() * 1000.0,
+                rule.dimacs_bytes
+            )?;
+            let lengths: Vec<_> = rule
+                .lengths
+                .iter()
+                .map(|(length, count)| format!("{count}×{length}"))
+                .collect();
+            writeln!(w, "  longueurs: {}", lengths.join(", "))?;
+        }
+
+        Ok(())
+    }
+
+    /// Écrit le rapport sur une seule ligne
+    pub fn write_json(&self, mut w: impl Write) -> io::Result<()> {
+        serde_json::to_writer(&mut w, self)?;
+        writeln!(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_read;
+    use crate::progress::Silent;
+    use crate::rules::BalanceEncoding;
+
+    #[test]
+    fn empty_grid() {
+        let stats = analyse(&Grid::new(4), &EncodingOptions::default(), &mut Silent);
+
+        assert_eq!((stats.givens, stats.variables, stats.removed), (0, 16, 0));
+        assert_eq!(stats.clauses, 64 + 32 + 12 * 256);
+        assert_eq!(stats.rules[0].lengths.get(&3), Some(&64));
+        assert_eq!(stats.rules[2].lengths.get(&8), Some(&(12 * 256)));
+
+        // La taille est exacte: c'est celle du fichier écrit par `cnf`
+        let mut cnf = CNFFile::new(&Grid::new(4), Vec::new());
+        rules::write_all(&mut cnf, &Grid::new(4));
+        assert_eq!(stats.dimacs_bytes, cnf.save().unwrap().len());
+    }
+
+    #[test]
+    fn givens_and_aux() {
+        let grid = grid_read::parse("4\n.1.0\n..0.\n.0..\n11.0\n").unwrap();
+        let options = EncodingOptions {
+            balance: BalanceEncoding::Sequential,
+            ..EncodingOptions::default()
+        };
+        let stats = analyse(&grid, &options, &mut Silent);
+
+        assert_eq!(stats.givens, 7);
+        assert!(stats.removed > 0);
+        assert_eq!(
+            stats.removed,
+            stats.rules.iter().map(|rule| rule.removed).sum::<usize>()
+        );
+        assert_eq!(stats.aux_variables, stats.rules[0].aux_variables);
+
+        let mut text = Vec::new();
+        stats.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with("grille 4x4, 7 cellules données\n"));
+        assert!(text.contains("\nrègle 2: "));
+
+        let mut json = Vec::new();
+        stats.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["rules"][1]["rule"], 2);
+        assert!(json["rules"][1]["lengths"]["3"].is_number());
+    }
+}
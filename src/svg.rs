@@ -0,0 +1,197 @@
+//! Rendu SVG d'une grille, éventuellement complétée par sa solution
+use crate::rules::RuleSet;
+use crate::{Cell, Grid};
+use std::fmt::Write;
+
+/// Options de rendu
+#[derive(Clone, Copy, Debug)]
+pub struct SvgOptions {
+    /// Côté d'une cellule, en pixels
+    pub cell_size: u32,
+
+    /// Numérote les colonnes (en haut) et les rangées (à gauche) à partir de 0, comme les
+    /// coordonnées `(x, y)` utilisées ailleurs
+    pub coordinates: bool,
+
+    /// Indique sous la grille les règles qui s'appliquent
+    pub rules: Option<RuleSet>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            cell_size: 40,
+            coordinates: false,
+            rules: None,
+        }
+    }
+}
+
+/// Décrit les règles en une courte ligne de texte
+fn rules_caption(rules: RuleSet) -> String {
+    let name = match rules {
+        RuleSet::TAKUZU => "Takuzu",
+        RuleSet::UNRULY => "Unruly",
+        _ => "variante",
+    };
+
+    let markers: Vec<_> = [
+        (rules.balance, "équilibre"),
+        (rules.no_triples, "pas de triplets"),
+        (rules.unique_lines, "lignes uniques"),
+    ]
+    .iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, marker)| *marker)
+    .collect();
+
+    format!("{name} : {}", markers.join(", "))
+}
+
+/// Dessine `puzzle`; les cellules vides de `puzzle` sont complétées par celles de `solution`, dans
+/// un style plus clair que les cellules données
+pub fn render(puzzle: &Grid, solution: Option<&Grid>, options: &SvgOptions) -> String {
+    let size = puzzle.size() as u32;
+    let cell = options.cell_size;
+    let margin = if options.coordinates { cell / 2 + 4 } else { 4 };
+    let board = size * cell;
+    let footer = if options.rules.is_some() {
+        cell / 2 + 8
+    } else {
+        0
+    };
+    let width = margin + board + 4;
+    let height = margin + board + 4 + footer;
+    let font_size = cell * 3 / 5;
+
+    let mut svg = String::new();
+    let mut w = |args: std::fmt::Arguments| svg.write_fmt(args).unwrap();
+
+    w(format_args!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+        viewBox=\"0 0 {width} {height}\" font-family=\"sans-serif\">\n"
+    ));
+    w(format_args!(
+        "<rect width=\"{width}\" height=\"{height}\" fill=\"white\"/>\n"
+    ));
+
+    // Lignes de la grille: fines à l'intérieur, épaisses sur le bord
+    w(format_args!("<g stroke=\"black\" stroke-width=\"1\">\n"));
+    for i in 1..size {
+        let offset = margin + i * cell;
+        w(format_args!(
+            "<line x1=\"{offset}\" y1=\"{margin}\" x2=\"{offset}\" y2=\"{}\"/>\n",
+            margin + board,
+        ));
+        w(format_args!(
+            "<line x1=\"{margin}\" y1=\"{offset}\" x2=\"{}\" y2=\"{offset}\"/>\n",
+            margin + board,
+        ));
+    }
+    w(format_args!("</g>\n"));
+    w(format_args!(
+        "<rect x=\"{margin}\" y=\"{margin}\" width=\"{board}\" height=\"{board}\" fill=\"none\" \
+        stroke=\"black\" stroke-width=\"3\"/>\n"
+    ));
+
+    if options.coordinates {
+        w(format_args!(
+            "<g font-size=\"{}\" fill=\"#555\" text-anchor=\"middle\" dominant-baseline=\"central\">\n",
+            font_size / 2,
+        ));
+        for i in 0..size {
+            let center = margin + i * cell + cell / 2;
+            w(format_args!(
+                "<text x=\"{center}\" y=\"{}\">{i}</text>\n",
+                margin / 2
+            ));
+            w(format_args!(
+                "<text x=\"{}\" y=\"{center}\">{i}</text>\n",
+                margin / 2
+            ));
+        }
+        w(format_args!("</g>\n"));
+    }
+
+    w(format_args!(
+        "<g font-size=\"{font_size}\" text-anchor=\"middle\" dominant-baseline=\"central\">\n"
+    ));
+    for y in 0..puzzle.size() {
+        for x in 0..puzzle.size() {
+            let (value, given) = match (puzzle.get(x, y), solution.map(|s| s.get(x, y))) {
+                (Cell::Filled(value), _) => (value, true),
+                (Cell::Empty, Some(Cell::Filled(value))) => (value, false),
+                (Cell::Empty, _) => continue,
+            };
+
+            let style = if given {
+                "font-weight=\"bold\" fill=\"black\""
+            } else {
+                "fill=\"#999\""
+            };
+            w(format_args!(
+                "<text x=\"{}\" y=\"{}\" {style}>{}</text>\n",
+                margin + x as u32 * cell + cell / 2,
+                margin + y as u32 * cell + cell / 2,
+                value as u8,
+            ));
+        }
+    }
+    w(format_args!("</g>\n"));
+
+    if let Some(rules) = options.rules {
+        w(format_args!(
+            "<text x=\"{margin}\" y=\"{}\" font-size=\"{}\" fill=\"#555\">{}</text>\n",
+            margin + board + 4 + footer / 2 + font_size / 4,
+            font_size / 2,
+            rules_caption(rules),
+        ));
+    }
+
+    w(format_args!("</svg>\n"));
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_read;
+
+    #[test]
+    fn givens_and_solution() {
+        let puzzle = grid_read::parse("2\n1.\n..\n").unwrap();
+        let solution = grid_read::parse("2\n10\n01\n").unwrap();
+
+        let svg = render(&puzzle, Some(&solution), &SvgOptions::default());
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("font-weight=\"bold\"").count(), 1);
+        assert_eq!(svg.matches("fill=\"#999\"").count(), 3);
+        assert_eq!(svg.matches("<line ").count(), 2);
+    }
+
+    #[test]
+    fn puzzle_only() {
+        let puzzle = grid_read::parse("2\n1.\n..\n").unwrap();
+
+        let svg = render(&puzzle, None, &SvgOptions::default());
+
+        assert_eq!(svg.matches(">1</text>").count(), 1);
+        assert_eq!(svg.matches("fill=\"#999\"").count(), 0);
+    }
+
+    #[test]
+    fn coordinates_and_rules() {
+        let options = SvgOptions {
+            coordinates: true,
+            rules: Some(RuleSet::UNRULY),
+            ..SvgOptions::default()
+        };
+
+        let svg = render(&Grid::new(4), None, &options);
+
+        assert_eq!(svg.matches(">3</text>").count(), 2);
+        assert!(svg.contains("Unruly : équilibre, pas de triplets</text>"));
+    }
+}
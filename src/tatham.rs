@@ -0,0 +1,249 @@
+//! Identifiants de partie (« game ID ») d'Unruly, le Takuzu de la collection de Simon Tatham.
+//!
+//! Un identifiant est de la forme `<paramètres>:<description>`, par exemple `6x6u:...`:
+//!   - les paramètres donnent la largeur et la hauteur, puis `u` si l'unicité des lignes est
+//!     imposée et éventuellement `d` suivi d'une lettre de difficulté (ignorée ici);
+//!   - la description parcourt les cellules ligne par ligne. Une lettre minuscule place un 0 et
+//!     une majuscule un 1, après avoir sauté autant de cellules vides que son rang dans l'alphabet
+//!     (`a`: aucune, `b`: une...). `z` et `Z` sautent 25 cellules sans rien placer. La description
+//!     se termine par une lettre « sentinelle » qui tombe juste après la dernière cellule.
+//!
+//! Unruly impose toujours les règles 1 et 2; seule la règle 3 dépend du paramètre `u`.
+
+use crate::rules::RuleSet;
+use crate::{Cell, Grid};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Error {
+    /// L'identifiant ne contient pas de `:` séparant paramètres et description
+    MissingDescription,
+
+    /// Les paramètres ne sont pas reconnus, ou les dimensions ne sont pas paires
+    InvalidParams(String),
+
+    /// Les grilles non carrées d'Unruly ne sont pas prises en charge
+    NotSquare { width: usize, height: usize },
+
+    /// La description contient un caractère autre qu'une lettre
+    InvalidChar(char),
+
+    /// La description ne couvre pas exactement la grille
+    InvalidLength,
+
+    /// Unruly impose toujours les règles 1 et 2
+    UnsupportedRules(RuleSet),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::MissingDescription => write!(f, "description manquante (`:` attendu)"),
+            Error::InvalidParams(params) => write!(f, "paramètres invalides: {params:?}"),
+            Error::NotSquare { width, height } => {
+                write!(f, "grille {width}x{height} non carrée")
+            }
+            Error::InvalidChar(c) => write!(f, "caractère {c:?} inattendu dans la description"),
+            Error::InvalidLength => write!(f, "la description ne couvre pas la grille"),
+            Error::UnsupportedRules(rules) => {
+                write!(
+                    f,
+                    "règles `{}` non représentables dans Unruly",
+                    rules.name()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Lis les paramètres `<largeur>[x<hauteur>][u][d<difficulté>]`, renvoie la taille et les règles
+fn parse_params(params: &str) -> Result<(usize, RuleSet), Error> {
+    let invalid = || Error::InvalidParams(params.into());
+
+    fn number(s: &str) -> (Option<usize>, &str) {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        (s[..end].parse().ok(), &s[end..])
+    }
+
+    let (width, rest) = number(params);
+    let width = width.ok_or_else(invalid)?;
+    let (height, rest) = match rest.strip_prefix('x') {
+        Some(rest) => {
+            let (height, rest) = number(rest);
+            (height.ok_or_else(invalid)?, rest)
+        }
+        None => (width, rest),
+    };
+
+    let (unique_lines, rest) = match rest.strip_prefix('u') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let rest = match rest.strip_prefix('d') {
+        Some(rest) => {
+            let mut chars = rest.chars();
+            chars
+                .next()
+                .filter(char::is_ascii_alphabetic)
+                .ok_or_else(invalid)?;
+            chars.as_str()
+        }
+        None => rest,
+    };
+
+    if !rest.is_empty() || width == 0 || width % 2 != 0 || height == 0 || height % 2 != 0 {
+        return Err(invalid());
+    }
+    if width != height {
+        return Err(Error::NotSquare { width, height });
+    }
+
+    Ok((
+        width,
+        RuleSet {
+            unique_lines,
+            ..RuleSet::UNRULY
+        },
+    ))
+}
+
+/// Lis un identifiant de partie Unruly
+pub fn parse(id: &str) -> Result<(Grid, RuleSet), Error> {
+    let (params, desc) = id.trim().split_once(':').ok_or(Error::MissingDescription)?;
+    let (size, rules) = parse_params(params)?;
+
+    let mut grid = Grid::new(size);
+    let cells = size * size;
+    let mut pos = 0;
+
+    for c in desc.chars() {
+        let (skip, value) = match c {
+            'z' | 'Z' => {
+                pos += 25;
+                continue;
+            }
+            'a'..='y' => (c as usize - 'a' as usize, false),
+            'A'..='Y' => (c as usize - 'A' as usize, true),
+            c => return Err(Error::InvalidChar(c)),
+        };
+
+        pos += skip;
+        if pos < cells {
+            grid.set(pos % size, pos / size, value);
+        }
+        pos += 1;
+    }
+
+    // La sentinelle finale place la position juste après la dernière cellule
+    if pos != cells + 1 {
+        return Err(Error::InvalidLength);
+    }
+
+    Ok((grid, rules))
+}
+
+/// Produit l'identifiant de partie Unruly de la grille, avec le paramètre `u` si `rules` impose
+/// l'unicité des lignes
+pub fn export(grid: &Grid, rules: RuleSet) -> Result<String, Error> {
+    if !rules.balance || !rules.no_triples {
+        return Err(Error::UnsupportedRules(rules));
+    }
+
+    let mut id = format!(
+        "{0}x{0}{1}:",
+        grid.size(),
+        if rules.unique_lines { "u" } else { "" },
+    );
+
+    /// Saute `run` cellules vides puis place un 0 (minuscule) ou un 1 (majuscule)
+    fn push(id: &mut String, mut run: usize, value: bool) {
+        let (skip, base) = if value { ('Z', b'A') } else { ('z', b'a') };
+        while run > 24 {
+            id.push(skip);
+            run -= 25;
+        }
+        id.push((base + run as u8) as char);
+    }
+
+    let mut run = 0;
+    for cell in grid.inner() {
+        match *cell {
+            Cell::Empty => run += 1,
+            Cell::Filled(value) => {
+                push(&mut id, run, value);
+                run = 0;
+            }
+        }
+    }
+    // Sentinelle
+    push(&mut id, run, false);
+
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_read;
+
+    const GRID: &str = "4\n.1.0\n..0.\n.0..\n11.0\n";
+
+    #[test]
+    fn export_basic() {
+        let grid = grid_read::parse(GRID).unwrap();
+
+        assert_eq!(export(&grid, RuleSet::TAKUZU).unwrap(), "4x4u:BbccCAba",);
+        assert_eq!(export(&grid, RuleSet::UNRULY).unwrap(), "4x4:BbccCAba",);
+    }
+
+    #[test]
+    fn round_trip() {
+        let (grid, rules) = parse("4x4u:BbccCAba").unwrap();
+
+        assert_eq!(grid_read::format(&grid), GRID);
+        assert_eq!(rules, RuleSet::TAKUZU);
+        assert_eq!(export(&grid, rules).unwrap(), "4x4u:BbccCAba");
+    }
+
+    #[test]
+    fn long_runs() {
+        let mut grid = Grid::new(8);
+        grid.set(6, 7, true);
+
+        assert_eq!(export(&grid, RuleSet::UNRULY).unwrap(), "8x8:ZZMb");
+
+        let (parsed, rules) = parse("8x8dn:zzMb").unwrap();
+        assert_eq!(rules, RuleSet::UNRULY);
+        assert_eq!(parsed.inner(), grid.inner());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("4x4").unwrap_err(), Error::MissingDescription);
+        assert_eq!(
+            parse("5x5:a").unwrap_err(),
+            Error::InvalidParams("5x5".into()),
+        );
+        assert_eq!(
+            parse("6x8:a").unwrap_err(),
+            Error::NotSquare {
+                width: 6,
+                height: 8,
+            },
+        );
+        assert_eq!(parse("4x4:p!").unwrap_err(), Error::InvalidChar('!'));
+        assert_eq!(parse("4x4:b").unwrap_err(), Error::InvalidLength);
+        assert_eq!(parse("8x8:ZZM").unwrap_err(), Error::InvalidLength);
+
+        let rules = RuleSet {
+            balance: false,
+            ..RuleSet::TAKUZU
+        };
+        assert_eq!(
+            export(&Grid::new(4), rules).unwrap_err(),
+            Error::UnsupportedRules(rules),
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Rendu des grilles dans le terminal: couleurs ANSI et cadre en caractères Unicode, désactivés
+//! automatiquement quand la sortie standard n'est pas un terminal
+use inf402_takuzu::rules::RuleSet;
+use inf402_takuzu::validate;
+use inf402_takuzu::{Cell, Grid};
+use std::io::IsTerminal;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const RED_BACKGROUND: &str = "\x1b[41m";
+const REVERSE: &str = "\x1b[7m";
+
+/// Capacités du terminal utilisées pour le rendu
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TermStyle {
+    /// Couleurs ANSI: cellules données en gras, déduites en cyan, en conflit sur fond rouge
+    pub color: bool,
+
+    /// Cadre et cellules vides en caractères Unicode
+    pub unicode: bool,
+}
+
+impl TermStyle {
+    /// Rendu identique à `Grid::print`, pour les fichiers et les tubes
+    pub const PLAIN: TermStyle = TermStyle {
+        color: false,
+        unicode: false,
+    };
+
+    /// Active le rendu enrichi si la sortie standard est un terminal. Les couleurs restent
+    /// désactivées si la variable `NO_COLOR` est définie (https://no-color.org).
+    pub fn detect() -> Self {
+        let tty = std::io::stdout().is_terminal();
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+
+        TermStyle {
+            color: tty && !no_color,
+            unicode: tty,
+        }
+    }
+}
+
+/// Lignes de texte de la grille `puzzle` complétée par `solution`, toutes de même largeur visible.
+/// La cellule `cursor` est mise en vidéo inverse, ou entre crochets sans couleurs (cadre Unicode
+/// uniquement).
+pub fn lines(
+    puzzle: &Grid,
+    solution: Option<&Grid>,
+    rules: RuleSet,
+    style: TermStyle,
+    cursor: Option<(usize, usize)>,
+) -> Vec<String> {
+    let size = puzzle.size();
+
+    let mut shown = puzzle.clone();
+    if let Some(solution) = solution {
+        for y in 0..size {
+            for x in 0..size {
+                if let (Cell::Empty, Cell::Filled(value)) = (puzzle.get(x, y), solution.get(x, y)) {
+                    shown.set(x, y, value);
+                }
+            }
+        }
+    }
+    let conflicts = if style.color {
+        validate::conflicts(&shown, rules)
+    } else {
+        Default::default()
+    };
+
+    let mut lines = Vec::with_capacity(size + 2);
+    if style.unicode {
+        lines.push(format!("┌{}┐", "─".repeat(2 * size + 1)));
+    }
+
+    for y in 0..size {
+        let at_cursor = |x: usize| cursor == Some((x, y));
+        let brackets = style.unicode && !style.color;
+        let mut line = String::new();
+        if style.unicode {
+            line.push('│');
+        }
+
+        for x in 0..size {
+            if style.unicode {
+                line.push(
+                    match (
+                        brackets && at_cursor(x),
+                        brackets && x > 0 && at_cursor(x - 1),
+                    ) {
+                        (true, _) => '[',
+                        (_, true) => ']',
+                        _ => ' ',
+                    },
+                );
+            }
+
+            let text = match shown.get(x, y) {
+                Cell::Filled(true) => "1",
+                Cell::Filled(false) => "0",
+                Cell::Empty if style.unicode => "·",
+                Cell::Empty => ".",
+            };
+
+            if style.color && (shown.get(x, y) != Cell::Empty || at_cursor(x)) {
+                let given = puzzle.get(x, y) != Cell::Empty;
+                line.push_str(if given { BOLD } else { CYAN });
+                if conflicts.contains(&(x, y)) {
+                    line.push_str(RED_BACKGROUND);
+                }
+                if at_cursor(x) {
+                    line.push_str(REVERSE);
+                }
+                line.push_str(text);
+                line.push_str(RESET);
+            } else {
+                line.push_str(text);
+            }
+        }
+
+        if style.unicode {
+            line.push(if brackets && at_cursor(size - 1) {
+                ']'
+            } else {
+                ' '
+            });
+            line.push('│');
+        }
+        lines.push(line);
+    }
+
+    if style.unicode {
+        lines.push(format!("└{}┘", "─".repeat(2 * size + 1)));
+    }
+
+    lines
+}
+
+/// Affiche `puzzle`, dont les cellules vides sont complétées par celles de `solution`. Les
+/// cellules qui enfreignent `rules` sont signalées.
+pub fn render(puzzle: &Grid, solution: Option<&Grid>, rules: RuleSet, style: TermStyle) -> String {
+    let mut out = String::new();
+    for line in lines(puzzle, solution, rules, style, None) {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Affiche la grille seule à gauche et la grille complétée par `solution` à droite
+pub fn side_by_side(puzzle: &Grid, solution: &Grid, rules: RuleSet, style: TermStyle) -> String {
+    let left = lines(puzzle, None, rules, style, None);
+    let right = lines(puzzle, Some(solution), rules, style, None);
+
+    let mut out = String::new();
+    for (left, right) in left.iter().zip(&right) {
+        out.push_str(left);
+        out.push_str("   ");
+        out.push_str(right);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inf402_takuzu::grid_read;
+
+    const PUZZLE: &str = "4\n.1.0\n..0.\n.0..\n11.0\n";
+    const SOLUTION: &str = "4\n0110\n1001\n0011\n1100\n";
+
+    #[test]
+    fn plain() {
+        let puzzle = grid_read::parse(PUZZLE).unwrap();
+        let solution = grid_read::parse(SOLUTION).unwrap();
+
+        assert_eq!(
+            render(&puzzle, None, RuleSet::TAKUZU, TermStyle::PLAIN),
+            ".1.0\n..0.\n.0..\n11.0\n",
+        );
+        assert_eq!(
+            render(&puzzle, Some(&solution), RuleSet::TAKUZU, TermStyle::PLAIN),
+            "0110\n1001\n0011\n1100\n",
+        );
+        assert_eq!(
+            side_by_side(&puzzle, &solution, RuleSet::TAKUZU, TermStyle::PLAIN)
+                .lines()
+                .next(),
+            Some(".1.0   0110"),
+        );
+    }
+
+    #[test]
+    fn unicode() {
+        let puzzle = grid_read::parse(PUZZLE).unwrap();
+        let style = TermStyle {
+            color: false,
+            unicode: true,
+        };
+
+        assert_eq!(
+            render(&puzzle, None, RuleSet::TAKUZU, style),
+            "┌─────────┐\n\
+            │ · 1 · 0 │\n\
+            │ · · 0 · │\n\
+            │ · 0 · · │\n\
+            │ 1 1 · 0 │\n\
+            └─────────┘\n",
+        );
+
+        let lines = lines(&puzzle, None, RuleSet::TAKUZU, style, Some((3, 1)));
+        assert_eq!(lines[2], "│ · · 0[·]│");
+    }
+
+    #[test]
+    fn colors() {
+        let puzzle = grid_read::parse(PUZZLE).unwrap();
+        let solution = grid_read::parse(SOLUTION).unwrap();
+        let style = TermStyle {
+            color: true,
+            unicode: false,
+        };
+
+        let out = render(&puzzle, Some(&solution), RuleSet::TAKUZU, style);
+        assert_eq!(out.matches(BOLD).count(), 7);
+        assert_eq!(out.matches(CYAN).count(), 9);
+        assert_eq!(out.matches(RED_BACKGROUND).count(), 0);
+
+        let out = render(
+            &grid_read::parse("4\n000.\n....\n....\n....\n").unwrap(),
+            None,
+            RuleSet::TAKUZU,
+            style,
+        );
+        assert_eq!(out.matches(RED_BACKGROUND).count(), 3);
+    }
+}
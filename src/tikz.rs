@@ -0,0 +1,166 @@
+//! Export LaTeX: grilles sous forme d'images TikZ autonomes et statistiques de la formule sous
+//! forme de tableau, pour les rapports
+use crate::rules::RuleStats;
+use crate::{Cell, Grid};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Ce que l'image doit montrer
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Layout {
+    /// La grille seule
+    Puzzle,
+
+    /// La grille complétée par sa solution, les cellules déduites en gris
+    Solution,
+
+    /// La grille et sa solution côte à côte
+    SideBySide,
+}
+
+/// Dessine une grille dont le coin supérieur gauche est en `(x_offset, 0)`; l'axe des ordonnées
+/// de TikZ étant orienté vers le haut, la rangée `y` est placée en `-y`
+fn draw_grid(out: &mut String, puzzle: &Grid, solution: Option<&Grid>, x_offset: usize) {
+    let n = puzzle.size();
+
+    writeln!(out, "  \\begin{{scope}}[xshift={x_offset}cm]").unwrap();
+    writeln!(out, "    \\draw[step=1cm,thin] (0,0) grid ({n},-{n});").unwrap();
+    writeln!(out, "    \\draw[very thick] (0,0) rectangle ({n},-{n});").unwrap();
+
+    for y in 0..n {
+        for x in 0..n {
+            let (value, given) = match (puzzle.get(x, y), solution.map(|s| s.get(x, y))) {
+                (Cell::Filled(value), _) => (value, true),
+                (Cell::Empty, Some(Cell::Filled(value))) => (value, false),
+                (Cell::Empty, _) => continue,
+            };
+
+            let text = if given {
+                format!("\\textbf{{{}}}", value as u8)
+            } else {
+                format!("\\textcolor{{gray}}{{{}}}", value as u8)
+            };
+            writeln!(out, "    \\node at ({x}.5,-{y}.5) {{{text}}};").unwrap();
+        }
+    }
+
+    writeln!(out, "  \\end{{scope}}").unwrap();
+}
+
+/// Environnement `tikzpicture` seul, à inclure dans un document existant
+pub fn picture(puzzle: &Grid, solution: Option<&Grid>, layout: Layout) -> String {
+    let mut out = String::from("\\begin{tikzpicture}[font=\\large]\n");
+
+    match layout {
+        Layout::Puzzle => draw_grid(&mut out, puzzle, None, 0),
+        Layout::Solution => draw_grid(&mut out, puzzle, solution, 0),
+        Layout::SideBySide => {
+            draw_grid(&mut out, puzzle, None, 0);
+            draw_grid(&mut out, puzzle, solution, puzzle.size() + 1);
+        }
+    }
+
+    out.push_str("\\end{tikzpicture}\n");
+    out
+}
+
+/// Document `standalone` complet, compilable tel quel
+pub fn standalone(picture: &str) -> String {
+    format!(
+        "\\documentclass[tikz,border=2mm]{{standalone}}\n\
+        \\usepackage[T1]{{fontenc}}\n\
+        \\begin{{document}}\n\
+        {picture}\
+        \\end{{document}}\n"
+    )
+}
+
+/// Tableau `tabular` des statistiques de la formule, règle par règle. `givens` est le nombre de
+/// clauses unitaires issues des cellules données.
+pub fn stats_table(stats: &[RuleStats], givens: usize) -> String {
+    let mut out = String::from(
+        "\\begin{tabular}{lrrr}\n\
+        \\hline\n\
+        Règle & Clauses & Littéraux & Temps (ms) \\\\\n\
+        \\hline\n",
+    );
+
+    writeln!(out, "Cellules données & {givens} & {givens} & -- \\\\").unwrap();
+    for s in stats {
+        writeln!(
+            out,
+            "Règle {} & {} & {} & {:.2} \\\\",
+            s.rule,
+            s.clauses,
+            s.literals,
+            s.duration.as_secs_f64() * 1000.0,
+        )
+        .unwrap();
+    }
+
+    let clauses = givens + stats.iter().map(|s| s.clauses).sum::<usize>();
+    let literals = givens + stats.iter().map(|s| s.literals).sum::<usize>();
+    let duration: Duration = stats.iter().map(|s| s.duration).sum();
+    writeln!(
+        out,
+        "\\hline\nTotal & {clauses} & {literals} & {:.2} \\\\\n\\hline\n\\end{{tabular}}",
+        duration.as_secs_f64() * 1000.0,
+    )
+    .unwrap();
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid_read;
+
+    #[test]
+    fn layouts() {
+        let puzzle = grid_read::parse("2\n1.\n..\n").unwrap();
+        let solution = grid_read::parse("2\n10\n01\n").unwrap();
+
+        let tikz = picture(&puzzle, Some(&solution), Layout::Puzzle);
+        assert_eq!(tikz.matches("\\node").count(), 1);
+        assert!(tikz.contains("\\node at (0.5,-0.5) {\\textbf{1}};"));
+
+        let tikz = picture(&puzzle, Some(&solution), Layout::Solution);
+        assert_eq!(tikz.matches("\\textbf").count(), 1);
+        assert_eq!(tikz.matches("\\textcolor{gray}").count(), 3);
+
+        let tikz = picture(&puzzle, Some(&solution), Layout::SideBySide);
+        assert_eq!(tikz.matches("\\begin{scope}").count(), 2);
+        assert!(tikz.contains("[xshift=3cm]"));
+        assert_eq!(tikz.matches("\\node").count(), 5);
+
+        let document = standalone(&tikz);
+        assert!(document.starts_with("\\documentclass"));
+        assert!(document.ends_with("\\end{tikzpicture}\n\\end{document}\n"));
+    }
+
+    #[test]
+    fn table() {
+        let stats = [
+            RuleStats {
+                rule: 1,
+                clauses: 10,
+                literals: 30,
+                duration: Duration::from_millis(3),
+                ..RuleStats::default()
+            },
+            RuleStats {
+                rule: 3,
+                clauses: 5,
+                literals: 20,
+                ..RuleStats::default()
+            },
+        ];
+
+        let table = stats_table(&stats, 2);
+
+        assert!(table.contains("Règle 1 & 10 & 30 & 3.00 \\\\"));
+        assert!(table.contains("Règle 3 & 5 & 20 & 0.00 \\\\"));
+        assert!(table.contains("Total & 17 & 52 & 3.00 \\\\"));
+    }
+}
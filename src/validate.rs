@@ -0,0 +1,197 @@
+//! Vérification directe des règles sur une grille, indépendamment de l'encodage CNF
+use crate::bitboard::{self, Bitboard};
+use crate::rules::RuleSet;
+use crate::{Cell, Grid};
+use std::collections::HashSet;
+
+/// Coordonnées `(x, y)` des cellules de la ligne (`column == false`) ou colonne numéro `k`
+fn line(size: usize, k: usize, column: bool) -> impl Iterator<Item = (usize, usize)> {
+    (0..size).map(move |i| if column { (k, i) } else { (i, k) })
+}
+
+/// Cellules qui enfreignent une des règles de `rules`. La grille peut être incomplète: seules les
+/// infractions déjà certaines sont signalées (trois cellules identiques consécutives, plus de la
+/// moitié d'une ligne de la même valeur, deux lignes complètes identiques).
+pub fn conflicts(grid: &Grid, rules: RuleSet) -> HashSet<(usize, usize)> {
+    match Bitboard::new(grid) {
+        Some(board) => board_conflicts(&board, rules),
+        None => cell_conflicts(grid, rules),
+    }
+}
+
+/// [`conflicts`], ligne par ligne sur les masques de bits
+pub fn board_conflicts(board: &Bitboard, rules: RuleSet) -> HashSet<(usize, usize)> {
+    let size = board.size();
+    let mut conflicts = HashSet::new();
+
+    for column in [false, true] {
+        let lines = board.lines(column);
+
+        for (k, line) in lines.iter().enumerate() {
+            let mut cells = 0;
+            for value in [false, true] {
+                if rules.no_triples {
+                    cells |= line.triples(value);
+                }
+                if rules.balance && line.count(value) > size / 2 {
+                    cells |= line.cells(value);
+                }
+            }
+            conflicts.extend(bitboard::bits(cells).map(|i| bitboard::position(k, i, column)));
+        }
+
+        if rules.unique_lines {
+            for a in 0..size {
+                for b in (a + 1)..size {
+                    if lines[a].is_complete(size) && lines[a] == lines[b] {
+                        conflicts.extend((0..size).map(|i| bitboard::position(a, i, column)));
+                        conflicts.extend((0..size).map(|i| bitboard::position(b, i, column)));
+                    }
+                }
+            }
+        }
+    }
+
+    conflicts
+}
+
+/// [`conflicts`], cellule par cellule, pour les grilles trop grandes pour un [`Bitboard`]
+fn cell_conflicts(grid: &Grid, rules: RuleSet) -> HashSet<(usize, usize)> {
+    let size = grid.size();
+    let mut conflicts = HashSet::new();
+
+    for column in [false, true] {
+        for k in 0..size {
+            let cells: Vec<_> = line(size, k, column).collect();
+
+            if rules.no_triples {
+                for window in cells.windows(3) {
+                    let values: Vec<_> = window.iter().map(|&(x, y)| grid.get(x, y)).collect();
+                    if values[0] != Cell::Empty && values[0] == values[1] && values[1] == values[2]
+                    {
+                        conflicts.extend(window.iter().copied());
+                    }
+                }
+            }
+
+            if rules.balance {
+                for value in [false, true] {
+                    let same: Vec<_> = cells
+                        .iter()
+                        .copied()
+                        .filter(|&(x, y)| grid.get(x, y) == Cell::Filled(value))
+                        .collect();
+                    if same.len() > size / 2 {
+                        conflicts.extend(same);
+                    }
+                }
+            }
+        }
+
+        if rules.unique_lines {
+            let complete = |k: usize| {
+                line(size, k, column)
+                    .map(|(x, y)| match grid.get(x, y) {
+                        Cell::Filled(value) => Some(value),
+                        Cell::Empty => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+            };
+
+            let lines: Vec<_> = (0..size).map(complete).collect();
+            for a in 0..size {
+                for b in (a + 1)..size {
+                    if lines[a].is_some() && lines[a] == lines[b] {
+                        conflicts.extend(line(size, a, column));
+                        conflicts.extend(line(size, b, column));
+                    }
+                }
+            }
+        }
+    }
+
+    conflicts
+}
+
+/// Indique si la grille est complète et respecte toutes les règles de `rules`
+pub fn is_solution(grid: &Grid, rules: RuleSet) -> bool {
+    match Bitboard::new(grid) {
+        Some(board) => board.is_complete() && board_conflicts(&board, rules).is_empty(),
+        None => !grid.inner().contains(&Cell::Empty) && cell_conflicts(grid, rules).is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{self, XorShift};
+    use crate::grid_read;
+
+    #[test]
+    fn valid() {
+        let grid = grid_read::parse("4\n0110\n1001\n0011\n1100\n").unwrap();
+
+        assert!(conflicts(&grid, RuleSet::TAKUZU).is_empty());
+        assert!(is_solution(&grid, RuleSet::TAKUZU));
+        assert!(!is_solution(
+            &grid_read::parse("4\n0110\n1001\n0011\n110.\n").unwrap(),
+            RuleSet::TAKUZU,
+        ));
+    }
+
+    #[test]
+    fn triples() {
+        let grid = grid_read::parse("6\n000...\n......\n......\n......\n......\n......\n").unwrap();
+
+        let conflicts = conflicts(&grid, RuleSet::TAKUZU);
+
+        assert_eq!(
+            conflicts,
+            [(0, 0), (1, 0), (2, 0)].iter().copied().collect(),
+        );
+    }
+
+    #[test]
+    fn balance() {
+        let grid = grid_read::parse("6\n11.1.1\n......\n......\n......\n......\n......\n").unwrap();
+
+        let conflicts = conflicts(&grid, RuleSet::TAKUZU);
+
+        assert_eq!(
+            conflicts,
+            [(0, 0), (1, 0), (3, 0), (5, 0)].iter().copied().collect(),
+        );
+    }
+
+    /// Les masques de bits trouvent les mêmes infractions que le parcours cellule par cellule
+    #[test]
+    fn board_matches_cells() {
+        let mut rng = XorShift::new(5);
+        for size in [2, 4, 6, 8, 10] {
+            for _ in 0..200 {
+                let grid = generate::random_partial_grid(size, &mut rng);
+
+                for rules in [RuleSet::TAKUZU, RuleSet::UNRULY] {
+                    let board = Bitboard::new(&grid).unwrap();
+                    assert_eq!(
+                        board_conflicts(&board, rules),
+                        cell_conflicts(&grid, rules),
+                        "{}",
+                        grid_read::format(&grid)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unique_lines() {
+        let grid = grid_read::parse("4\n0101\n1010\n0101\n....\n").unwrap();
+
+        assert_eq!(
+            conflicts(&grid, RuleSet::TAKUZU),
+            (0..4).flat_map(|x| vec![(x, 0), (x, 2)]).collect(),
+        );
+        assert!(conflicts(&grid, RuleSet::UNRULY).is_empty());
+    }
+}
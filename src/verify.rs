@@ -0,0 +1,227 @@
+//! Vérification exhaustive des encodages sur les petites grilles.
+//!
+//! Pour une grille vide de taille 4 ou 6, chaque affectation des cellules doit satisfaire la
+//! formule de [`rules::encode`] (pour une valeur des variables auxiliaires) si et seulement si
+//! elle respecte les règles selon [`validate`], qui ne dépend pas de l'encodage. Les `2^(n*n)`
+//! affectations ne sont pas toutes construites, mais toutes sont couvertes:
+//!   - les modèles de la formule, restreints aux cellules, sont énumérés avec le SAT-solveur en
+//!     interdisant chaque grille trouvée; chacun doit respecter les règles;
+//!   - les grilles qui respectent les règles sont énumérées cellule par cellule, en abandonnant
+//!     une affectation partielle dès qu'elle enfreint une règle (toutes ses complétions aussi);
+//!     chacune doit être un des modèles.
+//!
+//! Les deux ensembles sont alors égaux; avec toutes les règles du Takuzu, leur taille doit être le
+//! nombre connu de grilles: 72 en 4x4 et 4140 en 6x6.
+use crate::backend::{self, SatBackend, VarisatBackend};
+use crate::bitboard::Bitboard;
+use crate::progress::Silent;
+use crate::rules::{self, EncodingOptions, RuleSet};
+use crate::solve;
+use crate::{validate, Cell, Grid};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use varisat::{CnfFormula, Lit};
+
+/// Taille maximale des grilles vérifiées: en 8x8, il y a déjà plus de quatre millions de grilles
+pub const MAX_SIZE: usize = 6;
+
+/// Nombre de grilles complètes qui respectent les trois règles, pour les tailles vérifiables
+pub fn known_count(size: usize) -> Option<usize> {
+    match size {
+        2 => Some(2),
+        4 => Some(72),
+        6 => Some(4140),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// La grille est trop grande pour être vérifiée
+    TooLarge(usize),
+
+    Solver(backend::Error),
+
+    /// Grille qui satisfait la formule mais enfreint les règles
+    RejectedModel(Grid),
+
+    /// Grille qui respecte les règles mais ne satisfait pas la formule
+    MissingSolution(Grid),
+
+    /// Le nombre de grilles n'est pas le nombre connu
+    Count {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::TooLarge(size) => write!(
+                f,
+                "grille {size}x{size} trop grande pour une vérification exhaustive (au plus \
+                {MAX_SIZE}x{MAX_SIZE})"
+            ),
+            Error::Solver(err) => write!(f, "{err}"),
+            Error::RejectedModel(grid) => write!(
+                f,
+                "la formule accepte une grille qui enfreint les règles:\n{}",
+                crate::grid_read::format(grid)
+            ),
+            Error::MissingSolution(grid) => write!(
+                f,
+                "la formule refuse une grille qui respecte les règles:\n{}",
+                crate::grid_read::format(grid)
+            ),
+            Error::Count { expected, found } => {
+                write!(f, "{found} grilles au lieu de {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<backend::Error> for Error {
+    fn from(err: backend::Error) -> Self {
+        Error::Solver(err)
+    }
+}
+
+/// Hypothèses ou clause qui fixent (`negate == false`) ou interdisent la grille complète `grid`
+fn grid_literals(grid: &Grid, negate: bool) -> Vec<Lit> {
+    grid.inner()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, cell)| match *cell {
+            Cell::Filled(value) => Some(Lit::from_index(index, value != negate)),
+            Cell::Empty => None,
+        })
+        .collect()
+}
+
+/// Complète `board` à partir de la cellule `index` de toutes les façons qui respectent `rules`
+fn extend(board: &mut Bitboard, index: usize, rules: RuleSet, solutions: &mut Vec<Grid>) {
+    if !validate::board_conflicts(board, rules).is_empty() {
+        return;
+    }
+
+    let size = board.size();
+    if index == size * size {
+        solutions.push(board.to_grid());
+        return;
+    }
+
+    let (x, y) = (index % size, index / size);
+    for value in [false, true] {
+        board.set(x, y, Cell::Filled(value));
+        extend(board, index + 1, rules, solutions);
+    }
+    board.set(x, y, Cell::Empty);
+}
+
+/// Vérifie que la formule des règles `options.rules`, avec les encodages de `options`, accepte
+/// exactement les grilles de taille `size` qui respectent ces règles, et renvoie leur nombre
+pub fn exhaustive(size: usize, options: &EncodingOptions) -> Result<usize, Error> {
+    if size > MAX_SIZE {
+        return Err(Error::TooLarge(size));
+    }
+
+    let (formula, _) = rules::encode(&Grid::new(size), options, &mut Silent);
+    let models = check_formula(size, options.rules, &formula)?;
+
+    if options.rules == RuleSet::TAKUZU {
+        if let Some(expected) = known_count(size).filter(|&count| count != models) {
+            return Err(Error::Count {
+                expected,
+                found: models,
+            });
+        }
+    }
+
+    Ok(models)
+}
+
+/// Vérifie que `formula` accepte exactement les grilles de taille `size` qui respectent `rules`
+fn check_formula(size: usize, rules: RuleSet, formula: &CnfFormula) -> Result<usize, Error> {
+    let mut solutions = Vec::new();
+    extend(
+        &mut Bitboard::empty(size).unwrap(),
+        0,
+        rules,
+        &mut solutions,
+    );
+
+    let mut solver = VarisatBackend::default();
+    solver.add_formula(formula);
+
+    let mut models = HashSet::new();
+    while solver.solve()? {
+        let model = solve::model_to_grid(size, &solver.model().unwrap());
+        if !validate::is_solution(&model, rules) {
+            return Err(Error::RejectedModel(model));
+        }
+        solver.add_clause(&grid_literals(&model, true));
+        models.insert(grid_literals(&model, false));
+    }
+
+    // Chaque modèle est une des solutions: il suffit qu'aucune ne manque
+    match solutions
+        .into_iter()
+        .find(|solution| !models.contains(&grid_literals(solution, false)))
+    {
+        Some(solution) => Err(Error::MissingSolution(solution)),
+        None => Ok(models.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{BalanceEncoding, UniquenessEncoding};
+
+    /// Toutes les combinaisons d'encodages, avec toutes les règles, et en 4x4 sans la règle 3
+    #[test]
+    fn encodings() {
+        for (size, rules) in [
+            (4, RuleSet::TAKUZU),
+            (4, RuleSet::UNRULY),
+            (6, RuleSet::TAKUZU),
+        ] {
+            for balance in BalanceEncoding::ALL {
+                for uniqueness in UniquenessEncoding::ALL {
+                    let options = EncodingOptions {
+                        rules,
+                        balance,
+                        uniqueness,
+                    };
+                    let count = exhaustive(size, &options).unwrap();
+                    if rules == RuleSet::TAKUZU {
+                        assert_eq!(Some(count), known_count(size));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            exhaustive(8, &EncodingOptions::default()),
+            Err(Error::TooLarge(8))
+        ));
+
+        // Une formule sans la règle 3 accepte des grilles qui l'enfreignent, et la formule
+        // complète refuse des grilles qui ne respectent que les deux premières règles
+        let encode = |rules: RuleSet| rules::encode(&Grid::new(4), &rules.into(), &mut Silent).0;
+        assert!(matches!(
+            check_formula(4, RuleSet::TAKUZU, &encode(RuleSet::UNRULY)),
+            Err(Error::RejectedModel(_))
+        ));
+        assert!(matches!(
+            check_formula(4, RuleSet::UNRULY, &encode(RuleSet::TAKUZU)),
+            Err(Error::MissingSolution(_))
+        ));
+    }
+}